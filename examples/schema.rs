@@ -3,7 +3,9 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use ancon_protocol_metadata::msg::{HandleAnswer, HandleMsg, InitMsg, QueryAnswer, QueryMsg};
+use ancon_protocol_metadata::msg::{
+    HandleAnswer, HandleMsg, InitMsg, MigrateMsg, QueryAnswer, QueryMsg,
+};
 use ancon_protocol_metadata::state::{File, Metadata, MetadataStorage, State, MetadataSchema};
 
 fn main() {
@@ -14,6 +16,7 @@ fn main() {
 
     export_schema(&schema_for!(InitMsg), &out_dir);
     export_schema(&schema_for!(HandleMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(HandleAnswer), &out_dir);
     export_schema(&schema_for!(QueryAnswer), &out_dir);
@@ -0,0 +1,210 @@
+use std::convert::TryFrom;
+use std::io::Cursor;
+
+use cosmwasm_std::{StdError, StdResult};
+use libipld::{cbor::DagCborCodec, codec::Codec, ipld::Ipld, Cid};
+
+/// A CARv1 archive: the roots named by its header and its `(cid, data)` sections in order
+#[derive(Clone, Debug, PartialEq)]
+pub struct Car {
+    pub roots: Vec<Cid>,
+    pub blocks: Vec<(Cid, Vec<u8>)>,
+}
+
+/// Parses a CARv1 archive. Block bytes are not checked against their CIDs here.
+pub fn read_car(bytes: &[u8]) -> StdResult<Car> {
+    let mut pos = 0;
+    let header = read_section(bytes, &mut pos)?.ok_or_else(|| car_err("missing header"))?;
+    let roots = decode_header(header)?;
+
+    let mut blocks = vec![];
+    while let Some(section) = read_section(bytes, &mut pos)? {
+        let mut cursor = Cursor::new(section);
+        let cid = Cid::read_bytes(&mut cursor).map_err(|e| StdError::parse_err("Cid", e))?;
+        blocks.push((cid, section[cursor.position() as usize..].to_vec()));
+    }
+    Ok(Car { roots, blocks })
+}
+
+fn decode_header(header: &[u8]) -> StdResult<Vec<Cid>> {
+    let map = match DagCborCodec.decode::<Ipld>(header) {
+        Ok(Ipld::Map(map)) => map,
+        Ok(_) => return Err(car_err("header is not a map")),
+        Err(e) => return Err(StdError::parse_err("CAR header", e)),
+    };
+    match map.get("version") {
+        Some(Ipld::Integer(1)) => {}
+        _ => return Err(car_err("only version 1 is supported")),
+    }
+    let roots = match map.get("roots") {
+        Some(Ipld::List(roots)) => roots
+            .iter()
+            .map(|root| match root {
+                Ipld::Link(cid) => Ok(*cid),
+                _ => Err(car_err("roots must be links")),
+            })
+            .collect::<StdResult<Vec<_>>>()?,
+        _ => return Err(car_err("missing roots")),
+    };
+    if roots.is_empty() {
+        return Err(car_err("no roots"));
+    }
+    Ok(roots)
+}
+
+/// The next length prefixed section, or None at the end of the archive
+fn read_section<'a>(bytes: &'a [u8], pos: &mut usize) -> StdResult<Option<&'a [u8]>> {
+    if *pos == bytes.len() {
+        return Ok(None);
+    }
+    let len = read_varint(bytes, pos)?;
+    let end = (*pos as u64)
+        .checked_add(len)
+        .filter(|end| *end <= bytes.len() as u64)
+        .ok_or_else(|| car_err("truncated section"))? as usize;
+    let section = &bytes[*pos..end];
+    *pos = end;
+    Ok(Some(section))
+}
+
+/// Unsigned LEB128, as used by multiformats
+fn read_varint(bytes: &[u8], pos: &mut usize) -> StdResult<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*pos).ok_or_else(|| car_err("truncated varint"))?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(car_err("varint is too long"))
+}
+
+fn car_err(msg: &str) -> StdError {
+    StdError::generic_err(format!("Invalid CAR: {}", msg))
+}
+
+/// Appends the CARv1 header naming `roots`
+pub fn write_header(car: &mut Vec<u8>, roots: &[Cid]) -> StdResult<()> {
+    let roots = roots
+        .iter()
+        .map(|root| Ipld::Link(*root))
+        .collect::<Vec<_>>();
+    let header = libipld::ipld!({ "roots": roots, "version": 1 });
+    let header = DagCborCodec
+        .encode(&header)
+        .map_err(|e| StdError::serialize_err("CAR header", e))?;
+    write_section(car, &header);
+    Ok(())
+}
+
+/// Appends one block section
+pub fn write_block(car: &mut Vec<u8>, cid: &Cid, data: &[u8]) {
+    let mut section = cid.to_bytes();
+    section.extend_from_slice(data);
+    write_section(car, &section);
+}
+
+fn write_section(car: &mut Vec<u8>, section: &[u8]) {
+    write_varint(car, section.len() as u64);
+    car.extend_from_slice(section);
+}
+
+/// Unsigned LEB128, as used by multiformats and protobuf
+pub fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Encodes where a paged export resumes: the path of blocks from the root to the block being walked, each
+/// with the number of its links already followed
+pub fn encode_cursor(path: &[(Cid, u32)]) -> StdResult<Vec<u8>> {
+    let path = path
+        .iter()
+        .map(|(cid, next)| Ipld::List(vec![Ipld::Link(*cid), Ipld::Integer((*next).into())]))
+        .collect();
+    DagCborCodec
+        .encode(&Ipld::List(path))
+        .map_err(|e| StdError::serialize_err("export cursor", e))
+}
+
+pub fn decode_cursor(bytes: &[u8]) -> StdResult<Vec<(Cid, u32)>> {
+    let invalid = || StdError::generic_err("Invalid export cursor");
+    let path = match DagCborCodec.decode::<Ipld>(bytes) {
+        Ok(Ipld::List(path)) if !path.is_empty() => path,
+        _ => return Err(invalid()),
+    };
+    path.iter()
+        .map(|frame| match frame {
+            Ipld::List(frame) => match frame.as_slice() {
+                [Ipld::Link(cid), Ipld::Integer(next)] => u32::try_from(*next)
+                    .map(|next| (*cid, next))
+                    .map_err(|_| invalid()),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) fn encode_car(roots: &[Cid], blocks: &[(Cid, Vec<u8>)]) -> Vec<u8> {
+    let mut car = vec![];
+    write_header(&mut car, roots).unwrap();
+    for (cid, data) in blocks {
+        write_block(&mut car, cid, data);
+    }
+    car
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libipld::{block::Block, cid::multihash::Code, ipld, store::DefaultParams};
+
+    #[test]
+    fn reads_car_v1() {
+        let leaf =
+            Block::<DefaultParams>::encode(DagCborCodec, Code::Sha2_256, &ipld!({"a": 1})).unwrap();
+        let root = Block::<DefaultParams>::encode(
+            DagCborCodec,
+            Code::Sha2_256,
+            &ipld!({"leaf": Ipld::Link(*leaf.cid())}),
+        )
+        .unwrap();
+        let blocks = vec![
+            (*root.cid(), root.data().to_vec()),
+            (*leaf.cid(), leaf.data().to_vec()),
+        ];
+
+        let car = encode_car(&[*root.cid()], &blocks);
+        assert_eq!(
+            read_car(&car).unwrap(),
+            Car {
+                roots: vec![*root.cid()],
+                blocks,
+            }
+        );
+
+        read_car(&car[..car.len() - 1]).unwrap_err();
+        read_car(&encode_car(&[], &[])).unwrap_err();
+        read_car(&[]).unwrap_err();
+    }
+
+    #[test]
+    fn export_cursors() {
+        let leaf =
+            Block::<DefaultParams>::encode(DagCborCodec, Code::Sha2_256, &ipld!({"a": 1})).unwrap();
+        let path = vec![(*leaf.cid(), 2), (*leaf.cid(), 0)];
+
+        let cursor = encode_cursor(&path).unwrap();
+        assert_eq!(decode_cursor(&cursor).unwrap(), path);
+        decode_cursor(b"not a cursor").unwrap_err();
+        decode_cursor(&encode_cursor(&[]).unwrap()).unwrap_err();
+        decode_cursor(&DagCborCodec.encode(&ipld!([[1, 2]])).unwrap()).unwrap_err();
+    }
+}
@@ -1,21 +1,82 @@
-use crate::state::{load_from_store, save_to_store};
 use cosmwasm_std::{
-    debug_print, from_binary, to_binary, Api, Binary, Env, Extern, HandleResponse, InitResponse,
-    Querier, StdError, StdResult, Storage,
+    debug_print, from_binary, log, to_binary, Api, BankMsg, Binary, CanonicalAddr,
+    Coin, CosmosMsg, Env, Extern, HandleResponse, HumanAddr, InitResponse, LogAttribute, MigrateResponse,
+    MigrateResult, Querier,
+    ReadonlyStorage, StdError, StdResult, Storage,
 };
 
-use crate::msg::{HandleAnswer, HandleMsg, InitMsg, QueryAnswer, QueryMsg};
-use crate::state::{config, MetadataSchema, MetadataStorage, State};
+use crate::car::{decode_cursor, encode_cursor, read_car, write_block, write_header};
+use crate::crypto::{check_public_key, verify_signature};
+use crate::did::{issuer_methods, qualify_id, to_document, validate_did, DID_PREFIX};
+use crate::documents::{decode_file, decode_metadata, to_dag_json};
+use crate::events::{codec_name, coins_attr, redact, write_log};
+use crate::fees::{add_coins, check_fee, quote_fees, sub_coins};
+use crate::license::{
+    check_payee, decode_license, encode_license, license_links, lineage_links, royalties,
+};
+use crate::msg::{
+    Cw721Approval, Document, DocumentFormat, FileEncoding, HandleAnswer, HandleMsg, InitMsg,
+    LegacyKey, MigrateMsg, MintProof, Op, QueryAnswer, QueryMsg, SchemaInfo, ViewerInfo,
+};
+use crate::migrations::{backfill_index, pending, rekey, run, UNVERSIONED};
+use crate::nft::{
+    check_metadata, check_viewing_key, hash_viewing_key, metadata_owner, private_metadata, public_metadata,
+};
+use crate::placeholders::resolve_placeholders;
+use crate::quota::{record_write, remaining_bytes};
+use crate::revocation::{check_index, encode_list, is_set, set_bit};
+use crate::schema::{compile, validate};
+use crate::state::{
+    append_attestation, append_signature, config, config_read,
+    contract_version, contract_version_read, has_path, load_attestations, load_collected_fees,
+    may_load_attested_claim,
+    load_nft_operators, load_usage, load_signatures, may_load_block_data, may_load_block_index,
+    save_block_data, save_path, ContractVersion, PREFIX_FILES, PREFIX_METADATA,
+    load_schemas, may_load_schema, save_schema, SchemaEntry,
+    may_load_credential, may_load_did, may_load_nft, may_load_nft_by_cid, may_load_provenance,
+    may_load_status_list, may_load_swap, may_load_swap_by_cid, may_load_viewing_key, relayers,
+    relayers_read, save_block_index, save_collected_fees, save_credential, save_did, save_nft, save_nft_by_cid,
+    save_nft_operators, save_provenance, save_status_list, save_usage, save_swap, save_swap_by_cid,
+    save_viewing_key, use_mint_nonce, Attestation, BlockIndex, CredentialRecord, CredentialState,
+    DidRecord, Expiration, FeeRate, LinkPolicy, Metadata, MetadataSchema, Quotas, MetadataStorage, Nft, NftApproval, Provenance,
+    RelayerKey, RelayerSet, Service, Signature, SignatureAlgo, SourceLicense, State, StatusList,
+    BlockTime, StatusListRef, StatusPurpose, Swap, SwapState, ValidationRules, VerificationMethod,
+};
+use crate::unixfs::encode_file;
+use crate::validation::{check_content_size, check_content_type, check_links, check_path, check_rules};
+use crate::vc::{check_validity, decode_jwt, verify_jwt, Jwt};
 
 use libipld::{
-    block::Block, cbor::DagCborCodec, cid::multihash::Code, ipld, ipld::Ipld, store::DefaultParams,
-    Cid, cid::CidGeneric
+    block::Block, cbor::DagCborCodec, cid::multihash::Code, codec::Codec, ipld, ipld::Ipld,
+    json::DagJsonCodec, store::DefaultParams, Cid, cid::CidGeneric
 };
 
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
 use std::str::FromStr;
 
 type IpldBlock = libipld::block::Block<DefaultParams>;
 
+/// Upper bound on the blocks visited when aggregating license terms
+const MAX_LINEAGE: usize = 64;
+
+/// Page size of `ExportCar` when the query sets none
+const DEFAULT_EXPORT_BYTES: u64 = 512 * 1024;
+
+/// Page size of list queries that set no `limit`, and the largest one they may ask for
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+/// Multicodec code of DAG-CBOR
+const DAG_CBOR: u64 = 0x71;
+
+/// Codecs of the blocks that can be imported: raw, DAG-PB, DAG-CBOR and DAG-JSON
+const BLOCK_CODECS: &[u64] = &[0x55, 0x70, DAG_CBOR, 0x0129];
+
+pub const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 pub fn init<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
@@ -25,20 +86,80 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         tag: msg.tag,
         paused: false,
         owner: deps.api.canonical_address(&env.message.sender)?,
+        fees: vec![],
+        quotas: Quotas::default(),
+        validation: ValidationRules::default(),
     };
 
     config(&mut deps.storage).save(&state)?;
+    contract_version(&mut deps.storage).save(&ContractVersion {
+        name: CONTRACT_NAME.to_string(),
+        version: CONTRACT_VERSION.to_string(),
+    })?;
 
 //    debug_print!("Contract was initialized by {}", env.message.sender);
 
     Ok(InitResponse::default())
 }
 
+/// Runs the migrations between the stored version and this one, in order
+pub fn migrate<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    _env: Env,
+    msg: MigrateMsg,
+) -> MigrateResult {
+    let (from_version, _) = upgrade(deps, &msg.legacy_keys)?;
+
+    Ok(MigrateResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "migrate"),
+            log("from_version", &from_version),
+            log("to_version", CONTRACT_VERSION),
+        ],
+        data: None,
+    })
+}
+
+/// Brings the store up to this version, returning the version it was at and how many legacy keys were moved
+fn upgrade<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    legacy_keys: &[LegacyKey],
+) -> StdResult<(String, u32)> {
+    let stored = contract_version_read(&deps.storage)
+        .may_load()?
+        .unwrap_or(ContractVersion {
+            name: CONTRACT_NAME.to_string(),
+            version: UNVERSIONED.to_string(),
+        });
+    if stored.name != CONTRACT_NAME {
+        return Err(StdError::generic_err(format!(
+            "Cannot migrate from {} to {}",
+            stored.name, CONTRACT_NAME
+        )));
+    }
+
+    let mut migrated = 0;
+    for migration in pending(&stored.version, CONTRACT_VERSION)? {
+        migrated += run(deps, migration, legacy_keys)?;
+    }
+    contract_version(&mut deps.storage).save(&ContractVersion {
+        name: CONTRACT_NAME.to_string(),
+        version: CONTRACT_VERSION.to_string(),
+    })?;
+
+    Ok((stored.version, migrated))
+}
+
 pub fn handle<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     msg: HandleMsg,
 ) -> StdResult<HandleResponse> {
+    // only metered writes book fees, so funds sent with anything else could never be withdrawn
+    if !env.message.sent_funds.is_empty() && !is_metered(&msg) {
+        return Err(StdError::generic_err("This message does not accept funds"));
+    }
     match msg {
         HandleMsg::AddFile {
             path,
@@ -46,363 +167,5647 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             time,
             content,
             mode,
-        } => add_file(deps, env, path, content_type, time, content, mode),
-        HandleMsg::AddMetadata { data, path } => add_metadata(deps, env, data, path),
+            private,
+            encoding,
+        } => add_file(
+            deps,
+            env,
+            path,
+            content_type,
+            time,
+            content,
+            mode,
+            private,
+            encoding,
+        ),
+        HandleMsg::AddMetadata {
+            data,
+            path,
+            signature,
+            issuer_key,
+            private,
+            link_policy,
+        } => add_metadata(deps, env, data, path, signature, issuer_key, private, link_policy),
+        HandleMsg::Attest {
+            cid,
+            pubkey,
+            algo,
+            signature,
+            claim,
+        } => attest(deps, env, cid, pubkey, algo, signature, claim),
+        HandleMsg::RegisterDid { did, controllers } => register_did(deps, env, did, controllers),
+        HandleMsg::AddVerificationMethod {
+            did,
+            id,
+            algo,
+            public_key,
+        } => add_verification_method(deps, env, did, id, algo, public_key),
+        HandleMsg::RemoveVerificationMethod { did, id } => {
+            remove_verification_method(deps, env, did, id)
+        }
+        HandleMsg::AddService {
+            did,
+            id,
+            service_type,
+            endpoint,
+        } => add_service(deps, env, did, id, service_type, endpoint),
+        HandleMsg::RemoveService { did, id } => remove_service(deps, env, did, id),
+        HandleMsg::SetControllers { did, controllers } => {
+            set_controllers(deps, env, did, controllers)
+        }
+        HandleMsg::SetRevoked {
+            issuer,
+            list_id,
+            index,
+            revoked,
+            purpose,
+        } => set_revoked(deps, env, issuer, list_id, index, revoked, purpose),
+        HandleMsg::LockForSwap {
+            cid,
+            target_chain,
+            recipient,
+            hashlock,
+            timeout_height,
+        } => lock_for_swap(deps, env, cid, target_chain, recipient, hashlock, timeout_height),
+        HandleMsg::ClaimSwap { preimage } => claim_swap(deps, env, preimage),
+        HandleMsg::RefundSwap { swap_id } => refund_swap(deps, env, swap_id),
+        HandleMsg::SetRelayers { keys, threshold } => set_relayers(deps, env, keys, threshold),
+        HandleMsg::MintFromRemote {
+            source_chain,
+            source_cid,
+            proof,
+            relayer_sig,
+        } => mint_from_remote(deps, env, source_chain, source_cid, proof, relayer_sig),
+        HandleMsg::MintNft {
+            token_id,
+            metadata_cid,
+            owner,
+            signature,
+        } => mint_nft(deps, env, token_id, metadata_cid, owner, signature),
+        HandleMsg::TransferNft {
+            recipient,
+            token_id,
+        } => transfer_nft(deps, env, recipient, token_id),
+        HandleMsg::Approve {
+            spender,
+            token_id,
+            expires,
+        } => approve(deps, env, spender, token_id, expires),
+        HandleMsg::Revoke { spender, token_id } => revoke(deps, env, spender, token_id),
+        HandleMsg::ApproveAll { operator, expires } => approve_all(deps, env, operator, expires),
+        HandleMsg::RevokeAll { operator } => revoke_all(deps, env, operator),
+        HandleMsg::SetViewingKey { key } => set_viewing_key(deps, env, key),
+        HandleMsg::SetFees { fees } => set_fees(deps, env, fees),
+        HandleMsg::WithdrawFees { recipient, amount } => {
+            withdraw_fees(deps, env, recipient, amount)
+        }
+        HandleMsg::SetQuotas {
+            max_bytes,
+            max_writes_per_block,
+        } => set_quotas(deps, env, max_bytes, max_writes_per_block),
+        HandleMsg::SetValidationRules { rules } => set_validation_rules(deps, env, rules),
+        HandleMsg::MigrateStorage { keys } => migrate_storage(deps, env, keys),
+        HandleMsg::Batch { ops } => batch(deps, env, ops),
+        HandleMsg::ImportCar { car } => import_car(deps, env, car),
+        HandleMsg::PutBlock { cid, data } => put_block(deps, env, cid, data),
+        HandleMsg::AddDag {
+            path,
+            dag_json,
+            private,
+            link_policy,
+        } => add_dag(deps, env, path, dag_json, private, link_policy),
+        HandleMsg::RegisterSchema {
+            name,
+            ipld_schema_dsl,
+        } => register_schema(deps, env, name, ipld_schema_dsl),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn add_metadata<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
-    _env: Env,
-    data: MetadataSchema,
+    env: Env,
+    mut data: MetadataSchema,
     path: String,
+    signature: Option<Signature>,
+    issuer_key: Option<Binary>,
+    private: bool,
+    link_policy: Option<LinkPolicy>,
 ) -> StdResult<HandleResponse> {
-    let refs: Vec<_> = data
-        .refs
-        .iter()
-        .map(|l| Ipld::Link(Cid::from_str(&l).unwrap()))
-        .collect();
-    let sources: Vec<_> = data
-        .sources
-        .iter()
-        .map(|l| Ipld::Link(Cid::from_str(&l).unwrap()))
-        .collect();
-
-    let block = Block::<DefaultParams>::encode(
-        DagCborCodec,
-        Code::Sha2_256,
-        &ipld!({
-            "name":data.name,
-            "description": data.description,
-            "image": data.image,
-            "sources": sources,
-            "parent": Ipld::Link(Cid::from_str(&data.parent).unwrap()),
-            "refs": refs,
-        }),
-    )
-    .unwrap();
+    // placeholders only name operations of a batch
+    resolve_placeholders(&mut data, &[])?;
+    let rules = config_read(&deps.storage).load()?.validation;
+    let written = store_metadata(
+        deps,
+        &env,
+        &rules,
+        data,
+        path,
+        signature,
+        issuer_key,
+        private,
+        link_policy,
+    )?;
+    let messages = meter_write(deps, &env, written.bytes, written.blocks)?;
 
-    let data = block.data().to_vec();
-    let cid = block.cid().to_string();
-    let mut composite: String = "".to_string();
-    // key: 'QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D::/'
-    // key: 'QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D::/name'
-    // key: 'QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D::/sources[0]'
-    composite.push_str(&cid);
-    composite.push_str("::");
-    composite.push_str(&path);
-
-    //Saves path & data to interal bincode2 storage
-    let callback = HandleAnswer::AddMetadata { cid: cid };
-
-    save_to_store(&mut deps.storage, &composite.into_bytes(), &data)?;
     Ok(HandleResponse {
-        messages: vec![],
-        log: vec![],
-        data: Some(to_binary(&callback)?),
+        messages,
+        log: written.log,
+        data: Some(to_binary(&HandleAnswer::AddMetadata {
+            cid: written.cid.to_string(),
+        })?),
     })
 }
 
-pub fn add_file<S: Storage, A: Api, Q: Querier>(
+/// What a write stored, so fees and quotas can be applied once per message
+struct Written {
+    cid: Cid,
+    bytes: u64,
+    blocks: u64,
+    log: Vec<LogAttribute>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn store_metadata<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
-    env: Env,
+    env: &Env,
+    rules: &ValidationRules,
+    data: MetadataSchema,
     path: String,
-    content_type: String,
-    time: u64,
-    content: Vec<u8>,
-    mode: String,
-) -> StdResult<HandleResponse> {
-    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    signature: Option<Signature>,
+    issuer_key: Option<Binary>,
+    private: bool,
+    link_policy: Option<LinkPolicy>,
+) -> StdResult<Written> {
+    check_path(rules, &path)?;
+    for license in data.licenses.iter().flatten() {
+        check_payee(&deps.api, &license.payee)?;
+    }
+    let licenses = encode_licenses(&data)?;
+    let (block, links) = encode_metadata_links(&data, &licenses)?;
+    check_content_size(rules, block.data().len())?;
+    check_link_policy(&deps.storage, rules, link_policy.as_ref(), &links)?;
+    if data.schema.is_some() {
+        let node = block.ipld().map_err(|e| StdError::parse_err("DAG-CBOR", e))?;
+        check_schema(&deps.storage, &node)?;
+    }
 
-    let path2 = path.clone();
-    let block = Block::<DefaultParams>::encode(
-        DagCborCodec,
-        Code::Sha2_256,
-        &ipld!({
-            "owner": sender_address_raw.to_string(),
-            "path": path,
-            "type": content_type,
-            "content": content,
-            "time": time,
-            "mode": mode
-        }),
-    )
-    .unwrap();
+    // did:xdv owners must resolve here and be controlled by the sender
+    if let Some(owner) = &data.owner {
+        if owner.starts_with(DID_PREFIX) {
+            load_controlled_did(deps, env, owner)?;
+        }
+    }
 
-    let data = block.data().to_vec();
-    let cid = block.cid().to_string();
-    let mut composite: String = "".to_string();
-    // key: 'QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D::/'
-    // key: 'QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D::/name'
-    // key: 'QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D::/sources[0]'
-    composite.push_str(&cid);
-    composite.push_str("::");
-    composite.push_str(&path2);
+    let credential = match &data.verified_credential {
+        Some(token) => Some(verify_credential(
+            &deps.storage,
+            token,
+            data.owner.as_deref(),
+            issuer_key,
+            env.block.time,
+        )?),
+        None => None,
+    };
 
-    //Saves path & data to interal bincode2 storage
-    let callback = HandleAnswer::AddFile { cid: cid };
+    // The author signs the canonical DAG-CBOR bytes, so the signature covers exactly what the CID addresses
+    if let Some(signature) = &signature {
+        verify_signature(
+            &signature.algo,
+            signature.pubkey.as_slice(),
+            block.data(),
+            signature.sig.as_slice(),
+        )?;
+    }
 
-    save_to_store(&mut deps.storage, &composite.into_bytes(), &data)?;
-    Ok(HandleResponse {
-        messages: vec![],
-        log: vec![],
-        data: Some(to_binary(&callback)?),
-    })
-}
+    let bytes = licenses
+        .iter()
+        .fold(block.data().len(), |total, license| total + license.data().len());
+    let blocks = 1 + licenses.len() as u64;
+    let owner = deps.api.canonical_address(&env.message.sender)?;
 
-pub fn query<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    msg: QueryMsg,
-) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::GetFile { cid, path } => get_file(deps, cid, path),
-        QueryMsg::GetMetadata { cid, path } => get_metadata(deps, cid, path),
+    for license in licenses {
+        save_metadata_block(&mut deps.storage, &license, &path, owner.clone())?;
+    }
+    index_block(&mut deps.storage, block.cid(), &path, owner, signature)?;
+    if let Some(record) = credential {
+        save_credential(&mut deps.storage, block.cid(), &record)?;
     }
-}
 
-fn get_metadata<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    cid: String,
-    path: String,
-) -> StdResult<Binary> {
-    let cid2 = cid.clone();
-    let try_cid = Cid::from_str(&cid).unwrap();
-    
-    // if try_cid.codec() > 0 {
-    //     panic!("Invalid CID");
-    // }
+    //Saves the block and its path under binary (cid, path) keys
+    save_block_data(&mut deps.storage, block.cid(), block.data());
+    save_path(&mut deps.storage, PREFIX_METADATA, block.cid(), &path)?;
 
-    let mut composite: String = "".to_string();
-    // key: 'QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D::/'
-    // key: 'QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D::/name'
-    // key: 'QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D::/sources[0]'
-    composite.push_str(&cid2);
-    composite.push_str("::");
-    composite.push_str(&path);
-
-    let result = load_from_store(&deps.storage, &composite.into_bytes());
-    let block = IpldBlock::new(try_cid, result.unwrap()).unwrap();
-    let response = QueryAnswer::GetMetadata {
-        data: block.data().to_vec(),
-    };
+    let mut attrs = write_log(
+        "add_metadata",
+        block.cid(),
+        block.data().len(),
+        &path,
+        &env.message.sender,
+        private,
+    );
+    attrs.push(log("parent", redact(&data.parent, private)));
 
-    //Returns the metadata loaded from store from a CID & transformed to an IpldBlock
-    Ok(to_binary(&response)?)
+    Ok(Written {
+        cid: *block.cid(),
+        bytes: bytes as u64,
+        blocks,
+        log: attrs,
+    })
 }
 
-fn get_file<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    cid: String,
-    path: String,
-) -> StdResult<Binary> {
-    let cid2 = cid.clone();
-    let try_cid = Cid::from_str(&cid).unwrap();
-    
-    // if try_cid.codec() > 0 {
-    //     panic!("Invalid CID");
-    // }
+/// Encodes metadata as the DAG-CBOR block that gets stored and signed
+pub fn encode_metadata(data: &MetadataSchema) -> StdResult<IpldBlock> {
+    encode_metadata_with_licenses(data, &encode_licenses(data)?)
+}
 
-    let mut composite: String = "".to_string();
-    // key: 'QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D::/'
-    // key: 'QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D::/name'
-    // key: 'QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D::/sources[0]'
-    composite.push_str(&cid2);
-    composite.push_str("::");
-    composite.push_str(&path);
+/// `encode_metadata` for callers that already encoded the license blocks of `data`
+pub fn encode_metadata_with_licenses(
+    data: &MetadataSchema,
+    licenses: &[IpldBlock],
+) -> StdResult<IpldBlock> {
+    encode_metadata_links(data, licenses).map(|(block, _)| block)
+}
 
-    let result = load_from_store(&deps.storage, &composite.into_bytes());
-    let block = IpldBlock::new(try_cid, result.unwrap()).unwrap();
+/// Encodes metadata and returns its parent, sources and refs links alongside the block
+fn encode_metadata_links(
+    data: &MetadataSchema,
+    licenses: &[IpldBlock],
+) -> StdResult<(IpldBlock, Vec<Cid>)> {
+    let parse = |link: &String| Cid::from_str(link).map_err(|e| StdError::parse_err("Cid", e));
+    let parent = parse(&data.parent)?;
+    let sources = data.sources.iter().map(parse).collect::<StdResult<Vec<_>>>()?;
+    let refs = data.refs.iter().map(parse).collect::<StdResult<Vec<_>>>()?;
+    let to_links = |cids: &[Cid]| cids.iter().map(|cid| Ipld::Link(*cid)).collect::<Vec<_>>();
 
-    let response = QueryAnswer::GetFile {
-        data: block.data().to_vec(),
-    };
+    let mut node = ipld!({
+        "name": data.name.clone(),
+        "description": data.description.clone(),
+        "image": data.image.clone(),
+        "sources": to_links(&sources),
+        "parent": Ipld::Link(parent),
+        "refs": to_links(&refs),
+    });
+    // Optional fields are left out when unset so existing documents keep their CID
+    if let Ipld::Map(map) = &mut node {
+        if let Some(owner) = &data.owner {
+            map.insert("owner".to_string(), Ipld::String(owner.clone()));
+        }
+        if let Some(token) = &data.verified_credential {
+            map.insert("verifiedCredential".to_string(), Ipld::String(token.clone()));
+        }
+        if let Some(schema) = &data.schema {
+            let schema = Cid::from_str(schema).map_err(|e| StdError::parse_err("Cid", e))?;
+            map.insert("schema".to_string(), Ipld::Link(schema));
+        }
+        if data.licenses.is_some() {
+            let links = licenses
+                .iter()
+                .map(|license| Ipld::Link(*license.cid()))
+                .collect();
+            map.insert("licenses".to_string(), Ipld::List(links));
+        }
+    }
 
-    Ok(to_binary(&response)?)
+    let block = Block::<DefaultParams>::encode(DagCborCodec, Code::Sha2_256, &node).unwrap();
+    let links = std::iter::once(parent).chain(sources).chain(refs).collect();
+
+    Ok((block, links))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, MOCK_CONTRACT_ADDR};
-    use cosmwasm_std::{coins, CosmosMsg};
+/// License sub-documents of a metadata document, one block per licensed source
+pub fn encode_licenses(data: &MetadataSchema) -> StdResult<Vec<IpldBlock>> {
+    match &data.licenses {
+        Some(licenses) => licenses
+            .iter()
+            .map(|license| encode_license(license, &data.sources))
+            .collect(),
+        None => Ok(vec![]),
+    }
+}
 
-    #[test]
-    fn proper_initialization() {
-        let mut deps = mock_dependencies(20, &coins(2, "token"));
+/// Validates a VC-JWT backing a metadata write and returns what gets stored for it.
+/// Without an explicit issuer key, the keys of a registered `did:xdv` issuer are used.
+fn verify_credential<S: ReadonlyStorage>(
+    storage: &S,
+    token: &str,
+    owner: Option<&str>,
+    issuer_key: Option<Binary>,
+    now: u64,
+) -> StdResult<CredentialRecord> {
+    let jwt = decode_jwt(token)?;
 
-        let msg = InitMsg { tag: "test".to_string() };
-        let env = mock_env("creator", &coins(1000, "xdv"));
+    // A DID issuer is only trusted through its registered keys; any other issuer is recorded as the key
+    // that signed, since its `iss` cannot be checked
+    let issuer = if jwt.claims.iss.starts_with("did:") {
+        if issuer_key.is_some() {
+            return Err(StdError::generic_err(
+                "Credentials of DID issuers are verified with the DID document, not an issuer key",
+            ));
+        }
+        verify_with_did(storage, &jwt)?;
+        jwt.claims.iss.clone()
+    } else {
+        let issuer_key = issuer_key.ok_or_else(|| {
+            StdError::generic_err("An issuer key is required to verify the credential")
+        })?;
+        verify_jwt(&jwt, issuer_key.as_slice())?;
+        key_fingerprint(issuer_key.as_slice())
+    };
+    check_validity(&jwt, now)?;
 
-        // we can just call .unwrap() to assert this was a success
-        let res = init(&mut deps, env, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+    let status_list = jwt.status_list()?;
+    if let Some(entry) = &status_list {
+        check_index(entry.index)?;
+        match credential_state(storage, entry)? {
+            CredentialState::Verified => {}
+            CredentialState::Revoked => {
+                return Err(StdError::generic_err("Verified credential has been revoked"))
+            }
+            CredentialState::Suspended => {
+                return Err(StdError::generic_err("Verified credential is suspended"))
+            }
+        }
     }
 
-    #[test]
-    fn add_metadata() {
-        let mut deps = mock_dependencies(20, &coins(2, "token"));
+    let subject = jwt
+        .subject()
+        .ok_or_else(|| StdError::generic_err("Verified credential has no credentialSubject.id"))?;
+    if owner != Some(subject) {
+        return Err(StdError::generic_err(
+            "Verified credential subject does not match the metadata owner",
+        ));
+    }
 
-        let amount = coins(40, "ETH");
-        let collateral = coins(1, "BTC");
-        let expires = 100_000;
-        let msg = InitMsg { tag: "test".to_string() };
-        let env = mock_env("creator", &collateral);
+    Ok(CredentialRecord {
+        id: jwt.claims.jti.clone(),
+        issuer,
+        subject: subject.to_string(),
+        issued_at: jwt.claims.iat,
+        not_before: jwt.claims.nbf,
+        expires: jwt.claims.exp,
+        verified_at: now,
+        status: CredentialState::Verified,
+        status_list,
+    })
+}
 
-        // we can just call .unwrap() to assert this was a success
-        let _ = init(&mut deps, env, msg).unwrap();
+/// Looks the entry up in the lists of the credential issuer, which must hold a list of the same purpose
+fn credential_state<S: ReadonlyStorage>(
+    storage: &S,
+    entry: &StatusListRef,
+) -> StdResult<CredentialState> {
+    let list = may_load_status_list(storage, &entry.issuer, &entry.list_id)?.ok_or_else(|| {
+        StdError::generic_err(format!(
+            "Status list {} of {} is not registered in this contract",
+            entry.list_id, entry.issuer
+        ))
+    })?;
+    if list.purpose != entry.purpose {
+        return Err(StdError::generic_err(format!(
+            "Status list {} is a {:?} list",
+            entry.list_id, list.purpose
+        )));
+    }
 
-        let data = MetadataSchema {
-            name: "XDV metadata sample: NFT".to_string(),
-            description: "testing sample".to_string(),
-            image:
-                "https://explore.ipld.io/#/explore/QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D"
-                    .to_string(),
-            sources: vec!["QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string()],
-            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
-            refs: vec![
-                "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
-                "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
-            ],
-        };
-        let cid = "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string();
-        // add metadata
+    Ok(match (is_set(&list.bits, entry.index), list.purpose) {
+        (false, _) => CredentialState::Verified,
+        (true, StatusPurpose::Revocation) => CredentialState::Revoked,
+        (true, StatusPurpose::Suspension) => CredentialState::Suspended,
+    })
+}
 
-        // add metadata - success message
-        let payload = HandleMsg::AddMetadata {
-            data: data,
-            path: "/".to_string(),
-        };
-        let resp: HandleResponse =
-            handle(&mut deps, mock_env("creator", &collateral), payload).unwrap();
+/// `sha256:<hex>` of a public key, naming an issuer that is not a DID
+pub fn key_fingerprint(key: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(key)))
+}
 
-        let b = resp.data.unwrap_or_default();
-        let object = from_binary(&b).unwrap();
-        match object {
-            HandleAnswer::AddFile { cid } => {}
-            HandleAnswer::AddMetadata { cid } => {
-                assert_eq!(
-                    cid,
-                    "bafyreicnuvbp2lhmanra7r5o564fo4n5hhynqmwqv5l3ymz27gqbmlf2xa"
-                );
-                assert_ne!(
-                    cid,
-                    "hhynqmwqv5l3ymz27gqbmlf2xabafyreicnuvbp2lhmanra7r5o564fo4n5"
-                );
-                assert_ne!(
-                    cid,
-                    "57576"
-                );
-                assert_ne!(
-                    cid,
-                    ""
-                );
-                assert_ne!(
-                    cid,
-                    "0"
-                );
+fn verify_with_did<S: ReadonlyStorage>(storage: &S, jwt: &Jwt) -> StdResult<()> {
+    let issuer = &jwt.claims.iss;
+    if !issuer.starts_with(DID_PREFIX) {
+        return Err(StdError::generic_err(format!(
+            "Issuer {} is not a DID registered in this contract",
+            issuer
+        )));
+    }
+    let record = may_load_did(storage, issuer)?
+        .ok_or_else(|| StdError::not_found(format!("DID {}", issuer)))?;
+
+    let methods = issuer_methods(issuer, &record, jwt.header.kid.as_deref())?;
+    if methods
+        .iter()
+        .any(|m| verify_jwt(jwt, m.public_key.as_slice()).is_ok())
+    {
+        Ok(())
+    } else {
+        Err(StdError::generic_err(format!(
+            "Verified credential is not signed by a key of {}",
+            issuer
+        )))
+    }
+}
+
+/// Records who wrote a CID and, when signed, who attested it
+fn index_block<S: Storage>(
+    storage: &mut S,
+    cid: &Cid,
+    path: &str,
+    owner: CanonicalAddr,
+    signature: Option<Signature>,
+) -> StdResult<()> {
+    let signer = signature.as_ref().map(|s| s.pubkey.clone());
+    let index = match may_load_block_index(storage, cid)? {
+        Some(mut index) => {
+            if index.signer.is_none() {
+                index.signer = signer;
             }
+            index
         }
+        None => BlockIndex {
+            path: path.to_string(),
+            owner,
+            signer,
+        },
+    };
+    save_block_index(storage, cid, &index)?;
+
+    if let Some(signature) = signature {
+        append_signature(storage, cid, &signature)?;
     }
-    
-    // fn add_file() {
-    //     let mut deps = mock_dependencies(20, &coins(2, "token"));
+    Ok(())
+}
 
-    //     let amount = coins(40, "ETH");
-    //     let collateral = coins(1, "BTC");
-    //     let expires = 100_000;
-    //     let msg = InitMsg {};
-    //     let env = mock_env("creator", &collateral);
+pub fn attest<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    cid: String,
+    pubkey: Binary,
+    algo: SignatureAlgo,
+    signature: Binary,
+    claim: String,
+) -> StdResult<HandleResponse> {
+    let try_cid = Cid::from_str(&cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    if may_load_block_index(&deps.storage, &try_cid)?.is_none() {
+        return Err(StdError::not_found(format!("Block {}", cid)));
+    }
 
-    //     // we can just call .unwrap() to assert this was a success
-    //     let _ = init(&mut deps, env, msg).unwrap();
+    if may_load_attested_claim(&deps.storage, &try_cid, pubkey.as_slice())?.is_some() {
+        return Err(StdError::generic_err(format!(
+            "Key {} already attested {}",
+            hex::encode(pubkey.as_slice()),
+            cid
+        )));
+    }
 
-    //     let data = MetadataSchema {
-            
-    //     };
-    //     let cid = "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string();
-    //     // add metadata
+    let payload = attestation_payload(&try_cid, &claim)?;
+    verify_signature(&algo, pubkey.as_slice(), &payload, signature.as_slice())?;
+    let bytes = pubkey.len() + signature.len() + claim.len();
+    let messages = meter_write(deps, &env, bytes as u64, 0)?;
 
-    //     // add metadata - success message
-    //     let payload = HandleMsg::AddFile {
-    //         data: data,
-    //         path: "/".to_string(),
-    //     };
-    //     let resp: HandleResponse =
-    //         handle(&mut deps, mock_env("creator", &collateral), payload).unwrap();
+    let attrs = vec![
+        log("action", "attest"),
+        log("cid", &cid),
+        log("pubkey", hex::encode(pubkey.as_slice())),
+        log("claim", &claim),
+    ];
+    let attestation = Attestation {
+        pubkey,
+        algo,
+        signature,
+        claim,
+        height: env.block.height,
+    };
+    append_attestation(&mut deps.storage, &try_cid, &attestation)?;
 
-    //     let b = resp.data.unwrap_or_default();
-    //     let object = from_binary(&b).unwrap();
-    //     match object {
-    //         HandleAnswer::AddFile { cid } => {
-    //             assert_eq!(
-    //             cid,
-    //             "bafyreicnuvbp2lhmanra7r5o564fo4n5hhynqmwqv5l3ymz27gqbmlf2xa"
-    //         );
-    //     }}
-    //         HandleAnswer::AddMetadata { cid } => {
-                
-    //     }
-    // }
-    #[test]
-    fn get_metadata() {
-        let mut deps = mock_dependencies(20, &coins(2, "token"));
+    Ok(HandleResponse {
+        messages,
+        log: attrs,
+        data: Some(to_binary(&HandleAnswer::Attest { cid })?),
+    })
+}
+
+/// Bytes an attester signs: the DAG-CBOR encoding of `{ "cid": <link>, "claim": <claim> }`
+pub fn attestation_payload(cid: &Cid, claim: &str) -> StdResult<Vec<u8>> {
+    DagCborCodec
+        .encode(&ipld!({
+            "cid": Ipld::Link(cid.clone()),
+            "claim": claim,
+        }))
+        .map_err(|e| StdError::serialize_err("Ipld", e))
+}
+
+/// DAG-CBOR payload a signer of a metadata block signs to mint it as `token_id`
+pub fn nft_mint_payload(cid: &Cid, token_id: &str) -> StdResult<Vec<u8>> {
+    DagCborCodec
+        .encode(&ipld!({
+            "cid": Ipld::Link(cid.clone()),
+            "mint": token_id,
+        }))
+        .map_err(|e| StdError::serialize_err("Ipld", e))
+}
+
+pub fn register_did<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    did: String,
+    controllers: Option<Vec<HumanAddr>>,
+) -> StdResult<HandleResponse> {
+    validate_did(&did)?;
+    if may_load_did(&deps.storage, &did)?.is_some() {
+        return Err(StdError::generic_err(format!("{} is already registered", did)));
+    }
+
+    let controllers = controllers.unwrap_or_else(|| vec![env.message.sender.clone()]);
+    if !controllers.contains(&env.message.sender) {
+        return Err(StdError::generic_err(
+            "The sender must be one of the controllers of a new DID",
+        ));
+    }
+    let attrs = vec![
+        log("action", "register_did"),
+        log("did", &did),
+        log("controllers", join_addresses(&controllers)),
+    ];
+    let controllers = canonical_controllers(deps, &controllers)?;
+
+    let record = DidRecord {
+        controllers,
+        verification_methods: vec![],
+        services: vec![],
+    };
+    save_did(&mut deps.storage, &did, &record)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: attrs,
+        data: Some(to_binary(&HandleAnswer::RegisterDid { did })?),
+    })
+}
+
+pub fn add_verification_method<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    did: String,
+    id: String,
+    algo: SignatureAlgo,
+    public_key: Binary,
+) -> StdResult<HandleResponse> {
+    let mut record = load_controlled_did(deps, &env, &did)?;
+    let id = qualify_id(&did, &id)?;
+    if record.verification_methods.iter().any(|m| m.id == id) {
+        return Err(StdError::generic_err(format!("{} already exists", id)));
+    }
+    check_public_key(&algo, public_key.as_slice())?;
+
+    let attrs = vec![
+        log("action", "add_verification_method"),
+        log("did", &did),
+        log("id", &id),
+    ];
+    record.verification_methods.push(VerificationMethod {
+        id,
+        algo,
+        public_key,
+    });
+    save_did(&mut deps.storage, &did, &record)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: attrs,
+        data: Some(to_binary(&HandleAnswer::AddVerificationMethod { did })?),
+    })
+}
+
+pub fn remove_verification_method<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    did: String,
+    id: String,
+) -> StdResult<HandleResponse> {
+    let mut record = load_controlled_did(deps, &env, &did)?;
+    let id = qualify_id(&did, &id)?;
+    let position = record
+        .verification_methods
+        .iter()
+        .position(|m| m.id == id)
+        .ok_or_else(|| StdError::not_found(format!("Verification method {}", id)))?;
+
+    record.verification_methods.remove(position);
+    save_did(&mut deps.storage, &did, &record)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "remove_verification_method"),
+            log("did", &did),
+            log("id", &id),
+        ],
+        data: Some(to_binary(&HandleAnswer::RemoveVerificationMethod { did })?),
+    })
+}
+
+pub fn add_service<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    did: String,
+    id: String,
+    service_type: String,
+    endpoint: String,
+) -> StdResult<HandleResponse> {
+    let mut record = load_controlled_did(deps, &env, &did)?;
+    let id = qualify_id(&did, &id)?;
+    if record.services.iter().any(|s| s.id == id) {
+        return Err(StdError::generic_err(format!("{} already exists", id)));
+    }
+
+    let attrs = vec![
+        log("action", "add_service"),
+        log("did", &did),
+        log("id", &id),
+    ];
+    record.services.push(Service {
+        id,
+        service_type,
+        endpoint,
+    });
+    save_did(&mut deps.storage, &did, &record)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: attrs,
+        data: Some(to_binary(&HandleAnswer::AddService { did })?),
+    })
+}
+
+pub fn remove_service<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    did: String,
+    id: String,
+) -> StdResult<HandleResponse> {
+    let mut record = load_controlled_did(deps, &env, &did)?;
+    let id = qualify_id(&did, &id)?;
+    let position = record
+        .services
+        .iter()
+        .position(|s| s.id == id)
+        .ok_or_else(|| StdError::not_found(format!("Service {}", id)))?;
+
+    record.services.remove(position);
+    save_did(&mut deps.storage, &did, &record)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "remove_service"),
+            log("did", &did),
+            log("id", &id),
+        ],
+        data: Some(to_binary(&HandleAnswer::RemoveService { did })?),
+    })
+}
+
+/// Replaces the controllers of a DID; any current controller may rotate them
+pub fn set_controllers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    did: String,
+    controllers: Vec<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    let mut record = load_controlled_did(deps, &env, &did)?;
+    let attrs = vec![
+        log("action", "set_controllers"),
+        log("did", &did),
+        log("controllers", join_addresses(&controllers)),
+    ];
+    record.controllers = canonical_controllers(deps, &controllers)?;
+    save_did(&mut deps.storage, &did, &record)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: attrs,
+        data: Some(to_binary(&HandleAnswer::SetControllers { did })?),
+    })
+}
+
+fn join_addresses(addresses: &[HumanAddr]) -> String {
+    addresses
+        .iter()
+        .map(|a| a.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn canonical_controllers<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    controllers: &[HumanAddr],
+) -> StdResult<Vec<CanonicalAddr>> {
+    if controllers.is_empty() {
+        return Err(StdError::generic_err("A DID needs at least one controller"));
+    }
+    let mut canonical = vec![];
+    for controller in controllers {
+        let address = deps.api.canonical_address(controller)?;
+        if !canonical.contains(&address) {
+            canonical.push(address);
+        }
+    }
+    Ok(canonical)
+}
+
+/// Flips a bit in a status list of `issuer`, creating the list on first use. Only controllers of the
+/// issuer DID may update its lists.
+#[allow(clippy::too_many_arguments)]
+pub fn set_revoked<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    issuer: String,
+    list_id: String,
+    index: u32,
+    revoked: bool,
+    purpose: StatusPurpose,
+) -> StdResult<HandleResponse> {
+    check_index(index)?;
+    load_controlled_did(deps, &env, &issuer)?;
+    let mut list = may_load_status_list(&deps.storage, &issuer, &list_id)?.unwrap_or(StatusList {
+        purpose,
+        bits: vec![],
+    });
+    if list.purpose == StatusPurpose::Revocation && !revoked && is_set(&list.bits, index) {
+        return Err(StdError::generic_err(
+            "Revocations cannot be undone; use a suspension list",
+        ));
+    }
+
+    set_bit(&mut list.bits, index, revoked);
+    save_status_list(&mut deps.storage, &issuer, &list_id, &list)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_revoked"),
+            log("issuer", &issuer),
+            log("list_id", &list_id),
+            log("index", index),
+            log("revoked", revoked),
+        ],
+        data: Some(to_binary(&HandleAnswer::SetRevoked { list_id })?),
+    })
+}
+
+/// Locks the token minted for a CID for transfer to `target_chain`, moving it into the contract's custody.
+/// The swap id is the hex encoded hashlock.
+pub fn lock_for_swap<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    cid: String,
+    target_chain: String,
+    recipient: String,
+    hashlock: Binary,
+    timeout_height: u64,
+) -> StdResult<HandleResponse> {
+    let try_cid = Cid::from_str(&cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    let token_id = may_load_nft_by_cid(&deps.storage, &try_cid)?
+        .ok_or_else(|| StdError::not_found(format!("Token for {}", cid)))?;
+    let mut nft = load_nft(&deps.storage, &token_id)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if !can_transfer(&deps.storage, &BlockTime::from(&env.block), &nft, &sender)? {
+        return Err(StdError::unauthorized());
+    }
+
+    if hashlock.len() != 32 {
+        return Err(StdError::generic_err("Hashlock must be a SHA-256 digest"));
+    }
+    if timeout_height <= env.block.height {
+        return Err(StdError::generic_err("Timeout height must be in the future"));
+    }
+    if target_chain.is_empty() || recipient.is_empty() {
+        return Err(StdError::generic_err("Target chain and recipient are required"));
+    }
+    if may_load_swap(&deps.storage, hashlock.as_slice())?.is_some() {
+        return Err(StdError::generic_err("Hashlock has already been used"));
+    }
+
+    // A CID can only be in one live swap, and stays put once it has moved
+    if let Some(previous) = may_load_swap_by_cid(&deps.storage, &try_cid)? {
+        if let Some(swap) = may_load_swap(&deps.storage, previous.as_slice())? {
+            match swap.state {
+                SwapState::Locked => {
+                    return Err(StdError::generic_err(format!(
+                        "{} is locked in swap {}",
+                        cid,
+                        hex::encode(previous.as_slice())
+                    )))
+                }
+                SwapState::Claimed => {
+                    return Err(StdError::generic_err(format!(
+                        "{} was already swapped to {}",
+                        cid, swap.target_chain
+                    )))
+                }
+                SwapState::Refunded => {}
+            }
+        }
+    }
+
+    let swap_id = hex::encode(hashlock.as_slice());
+    let holder = std::mem::replace(&mut nft.owner, deps.api.canonical_address(&env.contract.address)?);
+    nft.approvals.clear();
+    save_nft(&mut deps.storage, &token_id, &nft)?;
+    let swap = Swap {
+        cid: cid.clone(),
+        token_id: token_id.clone(),
+        holder,
+        sender,
+        target_chain: target_chain.clone(),
+        recipient: recipient.clone(),
+        hashlock: hashlock.clone(),
+        timeout_height,
+        state: SwapState::Locked,
+        preimage: None,
+        updated_height: env.block.height,
+    };
+    save_swap(&mut deps.storage, &swap)?;
+    save_swap_by_cid(&mut deps.storage, &try_cid, &hashlock)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "lock_for_swap"),
+            log("swap_id", &swap_id),
+            log("cid", &cid),
+            log("token_id", &token_id),
+            log("sender", env.message.sender.as_str()),
+            log("target_chain", &target_chain),
+            log("recipient", &recipient),
+            log("hashlock", &swap_id),
+            log("timeout_height", timeout_height.to_string()),
+        ],
+        data: Some(to_binary(&HandleAnswer::LockForSwap { swap_id })?),
+    })
+}
+
+/// Completes a swap with the preimage of its hashlock, before the timeout height. The token stays in the
+/// contract's custody for good, marking the CID as moved.
+pub fn claim_swap<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    preimage: Binary,
+) -> StdResult<HandleResponse> {
+    let hashlock = Sha256::digest(preimage.as_slice());
+    let swap_id = hex::encode(&hashlock);
+    let mut swap = may_load_swap(&deps.storage, &hashlock)?
+        .ok_or_else(|| StdError::not_found(format!("Swap {}", swap_id)))?;
+
+    if swap.state != SwapState::Locked {
+        return Err(StdError::generic_err(format!("Swap {} is not locked", swap_id)));
+    }
+    if env.block.height >= swap.timeout_height {
+        return Err(StdError::generic_err(format!("Swap {} has timed out", swap_id)));
+    }
+
+    swap.state = SwapState::Claimed;
+    swap.preimage = Some(preimage.clone());
+    swap.updated_height = env.block.height;
+    save_swap(&mut deps.storage, &swap)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "claim_swap"),
+            log("swap_id", &swap_id),
+            log("cid", &swap.cid),
+            log("token_id", &swap.token_id),
+            log("target_chain", &swap.target_chain),
+            log("recipient", &swap.recipient),
+            log("preimage", hex::encode(preimage.as_slice())),
+        ],
+        data: Some(to_binary(&HandleAnswer::ClaimSwap { swap_id })?),
+    })
+}
+
+/// Releases a lock that was not claimed in time and returns the token to its holder; only the original
+/// sender can refund
+pub fn refund_swap<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    swap_id: String,
+) -> StdResult<HandleResponse> {
+    let hashlock = hex::decode(&swap_id).map_err(|e| StdError::parse_err("swap_id", e))?;
+    let mut swap = may_load_swap(&deps.storage, &hashlock)?
+        .ok_or_else(|| StdError::not_found(format!("Swap {}", swap_id)))?;
+
+    if swap.sender != deps.api.canonical_address(&env.message.sender)? {
+        return Err(StdError::unauthorized());
+    }
+    if swap.state != SwapState::Locked {
+        return Err(StdError::generic_err(format!("Swap {} is not locked", swap_id)));
+    }
+    if env.block.height < swap.timeout_height {
+        return Err(StdError::generic_err(format!(
+            "Swap {} cannot be refunded before height {}",
+            swap_id, swap.timeout_height
+        )));
+    }
+
+    let mut nft = load_nft(&deps.storage, &swap.token_id)?;
+    nft.owner = swap.holder.clone();
+    save_nft(&mut deps.storage, &swap.token_id, &nft)?;
+    swap.state = SwapState::Refunded;
+    swap.updated_height = env.block.height;
+    save_swap(&mut deps.storage, &swap)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "refund_swap"),
+            log("swap_id", &swap_id),
+            log("cid", &swap.cid),
+            log("token_id", &swap.token_id),
+        ],
+        data: Some(to_binary(&HandleAnswer::RefundSwap { swap_id })?),
+    })
+}
+
+/// Replaces the relayer key set; only the contract owner can do this
+pub fn set_relayers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    keys: Vec<RelayerKey>,
+    threshold: u32,
+) -> StdResult<HandleResponse> {
+    let state = config_read(&deps.storage).load()?;
+    if state.owner != deps.api.canonical_address(&env.message.sender)? {
+        return Err(StdError::unauthorized());
+    }
+    if threshold == 0 || threshold as usize > keys.len() {
+        return Err(StdError::generic_err(
+            "Threshold must be between 1 and the number of relayer keys",
+        ));
+    }
+    for key in &keys {
+        check_public_key(&key.algo, key.pubkey.as_slice())?;
+    }
+
+    let attrs = vec![
+        log("action", "set_relayers"),
+        log("relayers", keys.len()),
+        log("threshold", threshold),
+    ];
+    relayers(&mut deps.storage).save(&RelayerSet { keys, threshold })?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: attrs,
+        data: Some(to_binary(&HandleAnswer::SetRelayers { threshold })?),
+    })
+}
+
+/// Stores a block relayed from another chain once enough registered relayers have signed for it
+pub fn mint_from_remote<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    source_chain: String,
+    source_cid: String,
+    proof: MintProof,
+    relayer_sig: Vec<Signature>,
+) -> StdResult<HandleResponse> {
+    let set = relayers_read(&deps.storage)
+        .may_load()?
+        .ok_or_else(|| StdError::generic_err("No relayers are registered"))?;
+
+    let cid = Cid::from_str(&source_cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    if cid.codec() != DAG_CBOR {
+        return Err(StdError::generic_err("Relayed blocks must be DAG-CBOR"));
+    }
+    // The CID has to recompute from the relayed bytes, which also have to decode
+    let block = IpldBlock::new(cid, proof.data.as_slice().to_vec()).map_err(|_| {
+        StdError::generic_err(format!("Relayed data does not hash to {}", source_cid))
+    })?;
+    block
+        .decode::<DagCborCodec, Ipld>()
+        .map_err(|e| StdError::parse_err("DAG-CBOR", e))?;
+
+    let payload = mint_payload(&env, &source_chain, block.cid(), &proof)?;
+    let signers = check_relayer_signatures(&set, &payload, &relayer_sig)?;
+
+    if may_load_provenance(&deps.storage, block.cid())?.is_some() {
+        return Err(StdError::generic_err(format!(
+            "{} has already been minted",
+            source_cid
+        )));
+    }
+    if !use_mint_nonce(&mut deps.storage, &source_chain, proof.nonce) {
+        return Err(StdError::generic_err(format!(
+            "Nonce {} from {} has already been minted",
+            proof.nonce, source_chain
+        )));
+    }
+
+    let owner = deps.api.canonical_address(&proof.recipient)?;
+    save_metadata_block(&mut deps.storage, &block, "/", owner)?;
+
+    let provenance = Provenance {
+        source_chain: source_chain.clone(),
+        tx_hash: proof.tx_hash.clone(),
+        nonce: proof.nonce,
+        relayers: signers,
+        height: env.block.height,
+    };
+    save_provenance(&mut deps.storage, block.cid(), &provenance)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "mint_from_remote"),
+            log("cid", &source_cid),
+            log("codec", codec_name(block.cid().codec())),
+            log("size", block.data().len()),
+            log("owner", proof.recipient.as_str()),
+            log("source_chain", &source_chain),
+            log("tx_hash", &proof.tx_hash),
+            log("nonce", proof.nonce.to_string()),
+            log("recipient", proof.recipient.as_str()),
+        ],
+        data: Some(to_binary(&HandleAnswer::MintFromRemote { cid: source_cid })?),
+    })
+}
+
+/// Bytes each relayer signs: the DAG-CBOR encoding of `{ "chain_id", "contract", "nonce", "recipient",
+/// "source_chain", "source_cid": <link>, "tx_hash" }`. The destination chain and contract keep signatures
+/// from being replayed on other deployments.
+pub fn mint_payload(
+    env: &Env,
+    source_chain: &str,
+    source_cid: &Cid,
+    proof: &MintProof,
+) -> StdResult<Vec<u8>> {
+    DagCborCodec
+        .encode(&ipld!({
+            "chain_id": env.block.chain_id.as_str(),
+            "contract": env.contract.address.as_str(),
+            "source_chain": source_chain,
+            "source_cid": Ipld::Link(source_cid.clone()),
+            "tx_hash": proof.tx_hash.clone(),
+            "nonce": proof.nonce,
+            "recipient": proof.recipient.as_str(),
+        }))
+        .map_err(|e| StdError::serialize_err("Ipld", e))
+}
+
+/// Verifies relayer signatures and returns the distinct keys that signed, failing below the threshold
+fn check_relayer_signatures(
+    set: &RelayerSet,
+    payload: &[u8],
+    signatures: &[Signature],
+) -> StdResult<Vec<Binary>> {
+    let mut signers: Vec<Binary> = vec![];
+    for signature in signatures {
+        let key = set
+            .keys
+            .iter()
+            .find(|k| k.pubkey == signature.pubkey && k.algo == signature.algo)
+            .ok_or_else(|| StdError::generic_err("Signature from an unregistered relayer"))?;
+        verify_signature(
+            &key.algo,
+            key.pubkey.as_slice(),
+            payload,
+            signature.sig.as_slice(),
+        )?;
+        if !signers.contains(&key.pubkey) {
+            signers.push(key.pubkey.clone());
+        }
+    }
+
+    if (signers.len() as u32) < set.threshold {
+        return Err(StdError::generic_err(format!(
+            "{} of {} required relayer signatures",
+            signers.len(),
+            set.threshold
+        )));
+    }
+    Ok(signers)
+}
+
+/// Mints a SNIP-721 token for a metadata block. The sender must be the `owner` of the document (or a
+/// controller of a `did:xdv` owner), unless a key that signed the document signs the mint.
+pub fn mint_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    token_id: String,
+    metadata_cid: String,
+    owner: Option<HumanAddr>,
+    signature: Option<Signature>,
+) -> StdResult<HandleResponse> {
+    if token_id.is_empty() {
+        return Err(StdError::generic_err("Token id cannot be empty"));
+    }
+    if may_load_nft(&deps.storage, &token_id)?.is_some() {
+        return Err(StdError::generic_err(format!("Token {} already exists", token_id)));
+    }
+
+    let cid = Cid::from_str(&metadata_cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    if let Some(existing) = may_load_nft_by_cid(&deps.storage, &cid)? {
+        return Err(StdError::generic_err(format!(
+            "{} is already minted as token {}",
+            metadata_cid, existing
+        )));
+    }
+    let node = load_block(&deps.storage, &cid)?
+        .decode::<DagCborCodec, Ipld>()
+        .map_err(|e| StdError::parse_err("DAG-CBOR", e))?;
+    check_metadata(&node)?;
+    check_minter(deps, &env, &cid, &token_id, &node, signature.as_ref())?;
+
+    let owner = owner.unwrap_or_else(|| env.message.sender.clone());
+    let attrs = vec![
+        log("action", "mint_nft"),
+        log("token_id", &token_id),
+        log("cid", &metadata_cid),
+        log("owner", owner.as_str()),
+    ];
+    let nft = Nft {
+        owner: deps.api.canonical_address(&owner)?,
+        metadata_cid,
+        approvals: vec![],
+    };
+    save_nft(&mut deps.storage, &token_id, &nft)?;
+    save_nft_by_cid(&mut deps.storage, &cid, &token_id)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: attrs,
+        data: Some(to_binary(&HandleAnswer::MintNft { token_id })?),
+    })
+}
+
+fn check_minter<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    cid: &Cid,
+    token_id: &str,
+    node: &Ipld,
+    signature: Option<&Signature>,
+) -> StdResult<()> {
+    if let Some(signature) = signature {
+        if !load_signatures(&deps.storage, cid)?
+            .iter()
+            .any(|s| s.pubkey == signature.pubkey)
+        {
+            return Err(StdError::generic_err(
+                "The mint must be signed by a key that signed the metadata",
+            ));
+        }
+        let payload = nft_mint_payload(cid, token_id)?;
+        return verify_signature(
+            &signature.algo,
+            signature.pubkey.as_slice(),
+            &payload,
+            signature.sig.as_slice(),
+        );
+    }
+
+    match metadata_owner(node) {
+        Some(owner) if owner.starts_with(DID_PREFIX) => {
+            load_controlled_did(deps, env, &owner).map(|_| ())
+        }
+        Some(owner) if owner == env.message.sender.as_str() => Ok(()),
+        _ => Err(StdError::unauthorized()),
+    }
+}
+
+pub fn transfer_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    recipient: HumanAddr,
+    token_id: String,
+) -> StdResult<HandleResponse> {
+    let mut nft = load_nft(&deps.storage, &token_id)?;
+    check_not_swapped(&deps.storage, &token_id, &nft)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if !can_transfer(&deps.storage, &BlockTime::from(&env.block), &nft, &sender)? {
+        return Err(StdError::unauthorized());
+    }
+
+    nft.owner = deps.api.canonical_address(&recipient)?;
+    nft.approvals.clear();
+    save_nft(&mut deps.storage, &token_id, &nft)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "transfer_nft"),
+            log("token_id", &token_id),
+            log("sender", env.message.sender.as_str()),
+            log("recipient", recipient.as_str()),
+        ],
+        data: Some(to_binary(&HandleAnswer::TransferNft { token_id })?),
+    })
+}
+
+/// Lets `spender` transfer one token; the owner or one of its operators can grant this
+pub fn approve<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    spender: HumanAddr,
+    token_id: String,
+    expires: Option<Expiration>,
+) -> StdResult<HandleResponse> {
+    let mut nft = load_nft(&deps.storage, &token_id)?;
+    check_not_swapped(&deps.storage, &token_id, &nft)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if nft.owner != sender && !is_operator(&deps.storage, &BlockTime::from(&env.block), &nft.owner, &sender)? {
+        return Err(StdError::unauthorized());
+    }
+
+    let attrs = vec![
+        log("action", "approve"),
+        log("token_id", &token_id),
+        log("spender", spender.as_str()),
+    ];
+    let spender = deps.api.canonical_address(&spender)?;
+    nft.approvals.retain(|a| a.spender != spender);
+    nft.approvals.push(NftApproval {
+        spender,
+        expires: expires.unwrap_or(Expiration::Never),
+    });
+    save_nft(&mut deps.storage, &token_id, &nft)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: attrs,
+        data: Some(to_binary(&HandleAnswer::Approve { token_id })?),
+    })
+}
+
+pub fn revoke<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    spender: HumanAddr,
+    token_id: String,
+) -> StdResult<HandleResponse> {
+    let mut nft = load_nft(&deps.storage, &token_id)?;
+    check_not_swapped(&deps.storage, &token_id, &nft)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if nft.owner != sender && !is_operator(&deps.storage, &BlockTime::from(&env.block), &nft.owner, &sender)? {
+        return Err(StdError::unauthorized());
+    }
+
+    let attrs = vec![
+        log("action", "revoke"),
+        log("token_id", &token_id),
+        log("spender", spender.as_str()),
+    ];
+    let spender = deps.api.canonical_address(&spender)?;
+    nft.approvals.retain(|a| a.spender != spender);
+    save_nft(&mut deps.storage, &token_id, &nft)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: attrs,
+        data: Some(to_binary(&HandleAnswer::Revoke { token_id })?),
+    })
+}
+
+/// Lets `operator` transfer and approve every token of the sender
+pub fn approve_all<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    operator: HumanAddr,
+    expires: Option<Expiration>,
+) -> StdResult<HandleResponse> {
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    let spender = deps.api.canonical_address(&operator)?;
+    let mut operators = load_nft_operators(&deps.storage, &owner)?;
+    operators.retain(|o| o.spender != spender);
+    operators.push(NftApproval {
+        spender,
+        expires: expires.unwrap_or(Expiration::Never),
+    });
+    save_nft_operators(&mut deps.storage, &owner, &operators)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "approve_all"),
+            log("owner", env.message.sender.as_str()),
+            log("operator", operator.as_str()),
+        ],
+        data: Some(to_binary(&HandleAnswer::ApproveAll { operator })?),
+    })
+}
+
+pub fn revoke_all<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    operator: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    let spender = deps.api.canonical_address(&operator)?;
+    let mut operators = load_nft_operators(&deps.storage, &owner)?;
+    operators.retain(|o| o.spender != spender);
+    save_nft_operators(&mut deps.storage, &owner, &operators)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "revoke_all"),
+            log("owner", env.message.sender.as_str()),
+            log("operator", operator.as_str()),
+        ],
+        data: Some(to_binary(&HandleAnswer::RevokeAll { operator })?),
+    })
+}
+
+pub fn set_viewing_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: String,
+) -> StdResult<HandleResponse> {
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    save_viewing_key(&mut deps.storage, &owner, &hash_viewing_key(&key))?;
+
+    // the key itself is never logged
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_viewing_key"),
+            log("owner", env.message.sender.as_str()),
+        ],
+        data: Some(to_binary(&HandleAnswer::SetViewingKey {
+            address: env.message.sender,
+        })?),
+    })
+}
+
+fn load_nft<S: ReadonlyStorage>(storage: &S, token_id: &str) -> StdResult<Nft> {
+    may_load_nft(storage, token_id)?.ok_or_else(|| StdError::not_found(format!("Token {}", token_id)))
+}
+
+fn is_operator<S: ReadonlyStorage>(
+    storage: &S,
+    block: &BlockTime,
+    owner: &CanonicalAddr,
+    addr: &CanonicalAddr,
+) -> StdResult<bool> {
+    Ok(load_nft_operators(storage, owner)?
+        .iter()
+        .any(|o| o.spender == *addr && !o.expires.is_expired(block)))
+}
+
+/// Tokens locked in a swap, or swapped away, cannot be transferred or approved
+fn check_not_swapped<S: ReadonlyStorage>(storage: &S, token_id: &str, nft: &Nft) -> StdResult<()> {
+    let cid = Cid::from_str(&nft.metadata_cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    let hashlock = match may_load_swap_by_cid(storage, &cid)? {
+        Some(hashlock) => hashlock,
+        None => return Ok(()),
+    };
+    match may_load_swap(storage, hashlock.as_slice())?.map(|swap| swap.state) {
+        Some(SwapState::Locked) => Err(StdError::generic_err(format!(
+            "Token {} is locked in swap {}",
+            token_id,
+            hex::encode(hashlock.as_slice())
+        ))),
+        Some(SwapState::Claimed) => Err(StdError::generic_err(format!(
+            "Token {} was swapped to another chain",
+            token_id
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// The owner, an approved spender or an operator of the owner
+fn can_transfer<S: ReadonlyStorage>(
+    storage: &S,
+    block: &BlockTime,
+    nft: &Nft,
+    addr: &CanonicalAddr,
+) -> StdResult<bool> {
+    if nft.owner == *addr
+        || nft
+            .approvals
+            .iter()
+            .any(|a| a.spender == *addr && !a.expires.is_expired(block))
+    {
+        return Ok(true);
+    }
+    is_operator(storage, block, &nft.owner, addr)
+}
+
+/// Messages that pay write fees
+fn is_metered(msg: &HandleMsg) -> bool {
+    matches!(
+        msg,
+        HandleMsg::AddFile { .. }
+            | HandleMsg::AddMetadata { .. }
+            | HandleMsg::Attest { .. }
+            | HandleMsg::PutBlock { .. }
+            | HandleMsg::AddDag { .. }
+            | HandleMsg::RegisterSchema { .. }
+            | HandleMsg::ImportCar { .. }
+            | HandleMsg::Batch { .. }
+    )
+}
+
+/// Applies the fee and the sender's quotas to a write of `bytes` spread over `blocks` blocks.
+/// The fee is booked as collected; the returned messages refund the rest of the funds sent.
+fn meter_write<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    bytes: u64,
+    blocks: u64,
+) -> StdResult<Vec<CosmosMsg>> {
+    meter_writes(deps, env, 1, bytes, blocks)
+}
+
+/// `meter_write` for a message carrying `ops` writes, each paying the flat fee
+fn meter_writes<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    ops: u64,
+    bytes: u64,
+    blocks: u64,
+) -> StdResult<Vec<CosmosMsg>> {
+    let state = config_read(&deps.storage).load()?;
+    let fee = check_fee(&state.fees, &env.message.sent_funds, ops, bytes)?;
+
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    let mut usage = load_usage(&deps.storage, &sender)?;
+    let writes = u32::try_from(ops).unwrap_or(u32::MAX);
+    record_write(&state.quotas, &mut usage, env.block.height, writes, bytes, blocks)?;
+    save_usage(&mut deps.storage, &sender, &usage)?;
+
+    if !fee.is_empty() {
+        let mut collected = load_collected_fees(&deps.storage)?;
+        add_coins(&mut collected, &fee)?;
+        save_collected_fees(&mut deps.storage, &collected)?;
+    }
+
+    let mut change = env.message.sent_funds.clone();
+    sub_coins(&mut change, &fee)?;
+    if change.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(vec![CosmosMsg::Bank(BankMsg::Send {
+        from_address: env.contract.address.clone(),
+        to_address: env.message.sender.clone(),
+        amount: change,
+    })])
+}
+
+pub fn set_fees<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    fees: Vec<FeeRate>,
+) -> StdResult<HandleResponse> {
+    let mut state = config_read(&deps.storage).load()?;
+    if state.owner != deps.api.canonical_address(&env.message.sender)? {
+        return Err(StdError::unauthorized());
+    }
+    for (i, rate) in fees.iter().enumerate() {
+        if rate.denom.is_empty() || fees[..i].iter().any(|r| r.denom == rate.denom) {
+            return Err(StdError::generic_err(format!(
+                "Invalid or repeated fee denom {}",
+                rate.denom
+            )));
+        }
+    }
+
+    state.fees = fees.clone();
+    config(&mut deps.storage).save(&state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_fees"),
+            log(
+                "denoms",
+                fees.iter()
+                    .map(|rate| rate.denom.as_str())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+        ],
+        data: Some(to_binary(&HandleAnswer::SetFees { fees })?),
+    })
+}
+
+pub fn set_quotas<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    max_bytes: Option<u64>,
+    max_writes_per_block: Option<u32>,
+) -> StdResult<HandleResponse> {
+    let mut state = config_read(&deps.storage).load()?;
+    if state.owner != deps.api.canonical_address(&env.message.sender)? {
+        return Err(StdError::unauthorized());
+    }
+
+    state.quotas = Quotas {
+        max_bytes,
+        max_writes_per_block,
+    };
+    config(&mut deps.storage).save(&state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "set_quotas")],
+        data: Some(to_binary(&HandleAnswer::SetQuotas {
+            quotas: state.quotas,
+        })?),
+    })
+}
+
+pub fn set_validation_rules<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rules: ValidationRules,
+) -> StdResult<HandleResponse> {
+    let mut state = config_read(&deps.storage).load()?;
+    if state.owner != deps.api.canonical_address(&env.message.sender)? {
+        return Err(StdError::unauthorized());
+    }
+    check_rules(&rules)?;
+
+    state.validation = rules;
+    config(&mut deps.storage).save(&state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "set_validation_rules")],
+        data: Some(to_binary(&HandleAnswer::SetValidationRules {
+            rules: state.validation,
+        })?),
+    })
+}
+
+/// Sends collected fees to `recipient`, all of them unless `amount` is given
+pub fn withdraw_fees<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    recipient: HumanAddr,
+    amount: Option<Vec<Coin>>,
+) -> StdResult<HandleResponse> {
+    let state = config_read(&deps.storage).load()?;
+    if state.owner != deps.api.canonical_address(&env.message.sender)? {
+        return Err(StdError::unauthorized());
+    }
+
+    let mut collected = load_collected_fees(&deps.storage)?;
+    let amount = amount.unwrap_or_else(|| collected.clone());
+    if amount.is_empty() {
+        return Err(StdError::generic_err("There are no fees to withdraw"));
+    }
+    sub_coins(&mut collected, &amount)?;
+    save_collected_fees(&mut deps.storage, &collected)?;
+
+    Ok(HandleResponse {
+        messages: vec![CosmosMsg::Bank(BankMsg::Send {
+            from_address: env.contract.address,
+            to_address: recipient.clone(),
+            amount: amount.clone(),
+        })],
+        log: vec![
+            log("action", "withdraw_fees"),
+            log("recipient", recipient.as_str()),
+            log("amount", coins_attr(&amount)),
+        ],
+        data: Some(to_binary(&HandleAnswer::WithdrawFees { amount })?),
+    })
+}
+
+/// Indexes a block and saves it under `path` in the metadata namespace
+fn save_metadata_block<S: Storage>(
+    storage: &mut S,
+    block: &IpldBlock,
+    path: &str,
+    owner: CanonicalAddr,
+) -> StdResult<()> {
+    index_block(storage, block.cid(), path, owner, None)?;
+    save_block_data(storage, block.cid(), block.data());
+    save_path(storage, PREFIX_METADATA, block.cid(), path)
+}
+
+fn load_block<S: ReadonlyStorage>(storage: &S, cid: &Cid) -> StdResult<IpldBlock> {
+    let data = may_load_block_data(storage, cid)
+        .ok_or_else(|| StdError::not_found(format!("Block {}", cid)))?;
+
+    IpldBlock::new(*cid, data).map_err(|e| StdError::parse_err("Block", e))
+}
+
+/// Runs pending migrations, for chains that never call `migrate`, then rewrites blocks stored under
+/// `"<cid>::<path>"` string keys into the binary namespaces. Storage cannot be iterated, so the owner passes
+/// the keys written before the upgrade; keys already moved are skipped.
+pub fn migrate_storage<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    keys: Vec<LegacyKey>,
+) -> StdResult<HandleResponse> {
+    let state = config_read(&deps.storage).load()?;
+    if state.owner != deps.api.canonical_address(&env.message.sender)? {
+        return Err(StdError::unauthorized());
+    }
+
+    let (from_version, mut migrated) = upgrade(deps, &keys)?;
+    for key in keys {
+        if rekey(&mut deps.storage, &key)? {
+            backfill_index(deps, &key)?;
+            migrated += 1;
+        }
+    }
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "migrate_storage"),
+            log("migrated", migrated),
+            log("from_version", &from_version),
+            log("to_version", CONTRACT_VERSION),
+        ],
+        data: Some(to_binary(&HandleAnswer::MigrateStorage { migrated })?),
+    })
+}
+
+/// Loads a registered DID, failing unless the sender is one of its controllers
+fn load_controlled_did<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    did: &str,
+) -> StdResult<DidRecord> {
+    let record = may_load_did(&deps.storage, did)?
+        .ok_or_else(|| StdError::not_found(format!("DID {}", did)))?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if !record.controllers.contains(&sender) {
+        return Err(StdError::unauthorized());
+    }
+    Ok(record)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn add_file<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    path: String,
+    content_type: String,
+    time: u64,
+    content: Vec<u8>,
+    mode: String,
+    private: bool,
+    encoding: FileEncoding,
+) -> StdResult<HandleResponse> {
+    let rules = config_read(&deps.storage).load()?.validation;
+    let written = store_file(
+        deps,
+        &env,
+        &rules,
+        path,
+        content_type,
+        time,
+        content,
+        mode,
+        private,
+        encoding,
+    )?;
+    let messages = meter_write(deps, &env, written.bytes, written.blocks)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: written.log,
+        data: Some(to_binary(&HandleAnswer::AddFile {
+            cid: written.cid.to_string(),
+        })?),
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn store_file<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    rules: &ValidationRules,
+    path: String,
+    content_type: String,
+    time: u64,
+    content: Vec<u8>,
+    mode: String,
+    private: bool,
+    encoding: FileEncoding,
+) -> StdResult<Written> {
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+
+    check_path(rules, &path)?;
+    check_content_type(rules, &content_type)?;
+    check_content_size(rules, content.len())?;
+
+    if encoding == FileEncoding::Unixfs {
+        return store_unixfs(deps, env, path, content, private);
+    }
+
+    let path2 = path.clone();
+    let block = Block::<DefaultParams>::encode(
+        DagCborCodec,
+        Code::Sha2_256,
+        &ipld!({
+            "owner": sender_address_raw.to_string(),
+            "path": path,
+            "type": content_type,
+            "content": content,
+            "time": time,
+            "mode": mode
+        }),
+    )
+    .unwrap();
+
+    index_block(&mut deps.storage, block.cid(), &path2, sender_address_raw, None)?;
+
+    //Saves the block and its path under binary (cid, path) keys
+    save_block_data(&mut deps.storage, block.cid(), block.data());
+    save_path(&mut deps.storage, PREFIX_FILES, block.cid(), &path2)?;
+
+    Ok(Written {
+        cid: *block.cid(),
+        bytes: block.data().len() as u64,
+        blocks: 1,
+        log: write_log(
+            "add_file",
+            block.cid(),
+            block.data().len(),
+            &path2,
+            &env.message.sender,
+            private,
+        ),
+    })
+}
+
+/// Stores `content` as the UnixFS DAG `ipfs add` would build, every node indexed for the sender. Only the
+/// root is saved under `path`.
+fn store_unixfs<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    path: String,
+    content: Vec<u8>,
+    private: bool,
+) -> StdResult<Written> {
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    let nodes = encode_file(&content)?;
+
+    let mut bytes = 0;
+    for node in &nodes {
+        index_block(&mut deps.storage, &node.cid, &path, owner.clone(), None)?;
+        save_block_data(&mut deps.storage, &node.cid, &node.data);
+        bytes += node.data.len() as u64;
+    }
+    let root = nodes
+        .last()
+        .ok_or_else(|| StdError::generic_err("UnixFS file has no root"))?;
+    save_path(&mut deps.storage, PREFIX_FILES, &root.cid, &path)?;
+
+    Ok(Written {
+        cid: root.cid,
+        bytes,
+        blocks: nodes.len() as u64,
+        log: write_log(
+            "add_file",
+            &root.cid,
+            root.data.len(),
+            &path,
+            &env.message.sender,
+            private,
+        ),
+    })
+}
+
+/// Stores an already encoded raw, DAG-PB, DAG-CBOR or DAG-JSON block for the sender
+fn store_block<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    rules: &ValidationRules,
+    cid: String,
+    data: Binary,
+) -> StdResult<Written> {
+    let cid = Cid::from_str(&cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    let block = verify_block(cid, data.as_slice().to_vec())?;
+    check_content_size(rules, block.data().len())?;
+
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    index_block(&mut deps.storage, &cid, "/", owner, None)?;
+    save_block_data(&mut deps.storage, &cid, block.data());
+
+    Ok(Written {
+        cid,
+        bytes: block.data().len() as u64,
+        blocks: 1,
+        log: vec![
+            log("action", "put_block"),
+            log("cid", cid),
+            log("codec", codec_name(cid.codec())),
+            log("size", block.data().len()),
+            log("owner", env.message.sender.as_str()),
+        ],
+    })
+}
+
+pub fn put_block<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    cid: String,
+    data: Binary,
+) -> StdResult<HandleResponse> {
+    let rules = config_read(&deps.storage).load()?.validation;
+    let written = store_block(deps, &env, &rules, cid, data)?;
+    let messages = meter_write(deps, &env, written.bytes, written.blocks)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: written.log,
+        data: Some(to_binary(&HandleAnswer::PutBlock {
+            cid: written.cid.to_string(),
+        })?),
+    })
+}
+
+pub fn add_dag<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    path: String,
+    dag_json: String,
+    private: bool,
+    link_policy: Option<LinkPolicy>,
+) -> StdResult<HandleResponse> {
+    let rules = config_read(&deps.storage).load()?.validation;
+    let written = store_dag(deps, &env, &rules, path, dag_json, private, link_policy)?;
+    let messages = meter_write(deps, &env, written.bytes, written.blocks)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: written.log,
+        data: Some(to_binary(&HandleAnswer::AddDag {
+            cid: written.cid.to_string(),
+        })?),
+    })
+}
+
+/// Re-encodes a DAG-JSON document as canonical DAG-CBOR and stores it like metadata, so path, owner and
+/// lineage lookups apply to it
+fn store_dag<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    rules: &ValidationRules,
+    path: String,
+    dag_json: String,
+    private: bool,
+    link_policy: Option<LinkPolicy>,
+) -> StdResult<Written> {
+    check_path(rules, &path)?;
+    let node = DagJsonCodec
+        .decode::<Ipld>(dag_json.as_bytes())
+        .map_err(|e| StdError::parse_err("DAG-JSON", e))?;
+    let block = IpldBlock::encode(DagCborCodec, Code::Sha2_256, &node)
+        .map_err(|e| StdError::serialize_err("DAG-CBOR", e))?;
+    check_content_size(rules, block.data().len())?;
+    check_schema(&deps.storage, &node)?;
+    let mut links: Vec<Cid> = vec![];
+    block
+        .references(&mut links)
+        .map_err(|e| StdError::parse_err("DAG-CBOR", e))?;
+    check_link_policy(&deps.storage, rules, link_policy.as_ref(), &links)?;
+
+    // did:xdv owners must resolve here and be controlled by the sender
+    if let Ipld::Map(map) = &node {
+        if let Some(Ipld::String(owner)) = map.get("owner") {
+            if owner.starts_with(DID_PREFIX) {
+                load_controlled_did(deps, env, owner)?;
+            }
+        }
+    }
+
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    index_block(&mut deps.storage, block.cid(), &path, owner, None)?;
+    save_block_data(&mut deps.storage, block.cid(), block.data());
+    save_path(&mut deps.storage, PREFIX_METADATA, block.cid(), &path)?;
+
+    Ok(Written {
+        cid: *block.cid(),
+        bytes: block.data().len() as u64,
+        blocks: 1,
+        log: write_log(
+            "add_dag",
+            block.cid(),
+            block.data().len(),
+            &path,
+            &env.message.sender,
+            private,
+        ),
+    })
+}
+
+/// Registers a schema as a DAG-CBOR block, so documents can name it by CID
+pub fn register_schema<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    name: String,
+    ipld_schema_dsl: String,
+) -> StdResult<HandleResponse> {
+    let record = compile(&name, &ipld_schema_dsl)?;
+    let block = IpldBlock::encode(DagCborCodec, Code::Sha2_256, &record)
+        .map_err(|e| StdError::serialize_err("DAG-CBOR", e))?;
+    let rules = config_read(&deps.storage).load()?.validation;
+    check_content_size(&rules, block.data().len())?;
+    if may_load_schema(&deps.storage, block.cid())?.is_some() {
+        return Err(StdError::generic_err(format!(
+            "Schema {} is already registered",
+            block.cid()
+        )));
+    }
+
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    index_block(&mut deps.storage, block.cid(), "/", owner.clone(), None)?;
+    save_block_data(&mut deps.storage, block.cid(), block.data());
+    let entry = SchemaEntry {
+        name: name.clone(),
+        cid: block.cid().to_string(),
+        owner,
+    };
+    save_schema(&mut deps.storage, block.cid(), &entry)?;
+    let messages = meter_write(deps, &env, block.data().len() as u64, 1)?;
+
+    let mut attrs = write_log(
+        "register_schema",
+        block.cid(),
+        block.data().len(),
+        "/",
+        &env.message.sender,
+        false,
+    );
+    attrs.push(log("name", name));
+    Ok(HandleResponse {
+        messages,
+        log: attrs,
+        data: Some(to_binary(&HandleAnswer::RegisterSchema {
+            cid: block.cid().to_string(),
+        })?),
+    })
+}
+
+/// Applies the contract's link policy, then the one given with the write
+fn check_link_policy<S: ReadonlyStorage>(
+    storage: &S,
+    rules: &ValidationRules,
+    link_policy: Option<&LinkPolicy>,
+    links: &[Cid],
+) -> StdResult<()> {
+    let is_stored = |cid: &Cid| Ok(may_load_block_data(storage, cid).is_some());
+    check_links(&rules.link_policy, links, is_stored)?;
+    match link_policy {
+        Some(link_policy) => check_links(link_policy, links, is_stored),
+        None => Ok(()),
+    }
+}
+
+/// Validates a document against the registered schema its `schema` key links to, if any
+fn check_schema<S: ReadonlyStorage>(storage: &S, node: &Ipld) -> StdResult<()> {
+    let schema = match node {
+        Ipld::Map(map) => match map.get("schema") {
+            Some(Ipld::Link(cid)) => *cid,
+            Some(_) => return Err(StdError::generic_err("schema must be a link")),
+            None => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    if may_load_schema(storage, &schema)?.is_none() {
+        return Err(StdError::generic_err(format!(
+            "Schema {} is not registered",
+            schema
+        )));
+    }
+    let record = load_block(storage, &schema)?
+        .ipld()
+        .map_err(|e| StdError::parse_err("DAG-CBOR", e))?;
+    validate(&record, node)
+}
+
+/// Checks that `data` hashes to `cid` and decodes under its codec
+fn verify_block(cid: Cid, data: Vec<u8>) -> StdResult<IpldBlock> {
+    if !BLOCK_CODECS.contains(&cid.codec()) {
+        return Err(StdError::generic_err(format!(
+            "Unsupported codec {} of {}",
+            codec_name(cid.codec()),
+            cid
+        )));
+    }
+    let block = IpldBlock::new(cid, data)
+        .map_err(|_| StdError::generic_err(format!("Block data does not hash to {}", cid)))?;
+    block
+        .ipld()
+        .map_err(|e| StdError::parse_err(codec_name(cid.codec()), e))?;
+    Ok(block)
+}
+
+/// Stores the blocks of a CARv1 archive for the sender and returns its roots, which must be among the
+/// blocks. An error reverts the message, so nothing is kept unless every block verifies.
+pub fn import_car<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    car: Binary,
+) -> StdResult<HandleResponse> {
+    let car = read_car(car.as_slice())?;
+    if car.blocks.is_empty() {
+        return Err(StdError::generic_err("The CAR has no blocks"));
+    }
+    let rules = config_read(&deps.storage).load()?.validation;
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut stored = BTreeSet::new();
+    let mut bytes = 0;
+    for (cid, data) in car.blocks {
+        // archives may repeat a block
+        if !stored.insert(cid) {
+            continue;
+        }
+        let block = verify_block(cid, data)?;
+        check_content_size(&rules, block.data().len())?;
+        index_block(&mut deps.storage, &cid, "/", owner.clone(), None)?;
+        save_block_data(&mut deps.storage, &cid, block.data());
+        bytes += block.data().len() as u64;
+    }
+    if let Some(root) = car.roots.iter().find(|root| !stored.contains(*root)) {
+        return Err(StdError::generic_err(format!("Root {} is not in the archive", root)));
+    }
+    let messages = meter_write(deps, &env, bytes, stored.len() as u64)?;
+
+    let roots: Vec<String> = car.roots.iter().map(|root| root.to_string()).collect();
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "import_car"),
+            log("roots", roots.join(",")),
+            log("blocks", stored.len()),
+            log("size", bytes),
+            log("owner", env.message.sender.as_str()),
+        ],
+        data: Some(to_binary(&HandleAnswer::ImportCar { roots })?),
+    })
+}
+
+/// Runs every operation or none: an error in any of them fails the message, which reverts the
+/// writes of the earlier ones. Fees and quotas are applied once, to the total written. Metadata links
+/// may be `"$op:N"` to name the CID written by an earlier operation.
+pub fn batch<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    ops: Vec<Op>,
+) -> StdResult<HandleResponse> {
+    if ops.is_empty() {
+        return Err(StdError::generic_err("A batch needs at least one operation"));
+    }
+    let rules = config_read(&deps.storage).load()?.validation;
+
+    let mut cids: Vec<Cid> = Vec::with_capacity(ops.len());
+    let mut attrs = vec![log("action", "batch"), log("ops", ops.len())];
+    let (mut bytes, mut blocks) = (0, 0);
+    for (i, op) in ops.into_iter().enumerate() {
+        let written = match op {
+            Op::AddFile {
+                path,
+                content_type,
+                time,
+                content,
+                mode,
+                private,
+                encoding,
+            } => store_file(
+                deps,
+                &env,
+                &rules,
+                path,
+                content_type,
+                time,
+                content,
+                mode,
+                private,
+                encoding,
+            ),
+            Op::AddMetadata {
+                mut data,
+                path,
+                signature,
+                issuer_key,
+                private,
+                link_policy,
+            } => resolve_placeholders(&mut data, &cids).and_then(|_| {
+                store_metadata(
+                    deps,
+                    &env,
+                    &rules,
+                    data,
+                    path,
+                    signature,
+                    issuer_key,
+                    private,
+                    link_policy,
+                )
+            }),
+            Op::PutBlock { cid, data } => store_block(deps, &env, &rules, cid, data),
+        }
+        .map_err(|e| op_err(i, e))?;
+
+        bytes += written.bytes;
+        blocks += written.blocks;
+        attrs.extend(written.log);
+        cids.push(written.cid);
+    }
+    let messages = meter_writes(deps, &env, cids.len() as u64, bytes, blocks)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: attrs,
+        data: Some(to_binary(&HandleAnswer::Batch {
+            cids: cids.iter().map(|cid| cid.to_string()).collect(),
+        })?),
+    })
+}
+
+/// Names the failing op of a batch in the error message, keeping the kind of error
+fn op_err(i: usize, err: StdError) -> StdError {
+    let at = |msg: &str| format!("Operation {} failed: {}", i, msg);
+    match err {
+        StdError::GenericErr { msg, .. } => StdError::generic_err(at(&msg)),
+        StdError::InvalidBase64 { msg, .. } => StdError::invalid_base64(at(&msg)),
+        StdError::InvalidUtf8 { msg, .. } => StdError::invalid_utf8(at(&msg)),
+        StdError::NotFound { kind, .. } => StdError::not_found(at(&kind)),
+        StdError::ParseErr { target, msg, .. } => StdError::parse_err(target, at(&msg)),
+        StdError::SerializeErr { source, msg, .. } => StdError::serialize_err(source, at(&msg)),
+        // errors without a message, such as unauthorized, are returned as they are
+        err => err,
+    }
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::GetFile { cid, path, format } => get_file(deps, cid, path, format),
+        QueryMsg::GetMetadata { cid, path, format } => get_metadata(deps, cid, path, format),
+        QueryMsg::GetSignatures { cid } => get_signatures(deps, cid),
+        QueryMsg::ListAttestations {
+            cid,
+            start_after,
+            limit,
+        } => list_attestations(deps, cid, start_after, limit),
+        QueryMsg::GetCredential { cid } => get_credential(deps, cid),
+        QueryMsg::ResolveDid { did } => resolve_did(deps, did),
+        QueryMsg::CredentialStatus {
+            issuer,
+            list_id,
+            index,
+        } => credential_status(deps, issuer, list_id, index),
+        QueryMsg::StatusList { issuer, list_id } => status_list(deps, issuer, list_id),
+        QueryMsg::SwapStatus { swap_id } => swap_status(deps, swap_id),
+        QueryMsg::GetProvenance { cid } => get_provenance(deps, cid),
+        QueryMsg::OwnerOf {
+            token_id,
+            viewer,
+            include_expired,
+            block,
+        } => owner_of(deps, token_id, viewer, include_expired.unwrap_or(false), block),
+        QueryMsg::NftInfo { token_id } => nft_info(deps, token_id),
+        QueryMsg::LicenseTerms { cid } => license_terms(deps, cid),
+        QueryMsg::EstimateFee { bytes, ops } => estimate_fee(deps, ops.unwrap_or(1), bytes),
+        QueryMsg::Usage { address } => usage(deps, address),
+        QueryMsg::ContractVersion {} => {
+            let ContractVersion { name, version } = contract_version_read(&deps.storage).load()?;
+            to_binary(&QueryAnswer::ContractVersion { name, version })
+        }
+        QueryMsg::GetBlock { cid } => get_block(deps, cid),
+        QueryMsg::ListSchemas { start_after, limit } => list_schemas(deps, start_after, limit),
+        QueryMsg::ExportCar {
+            root,
+            max_depth,
+            max_bytes,
+            cursor,
+        } => export_car(deps, root, max_depth, max_bytes, cursor),
+        QueryMsg::ValidationRules {} => to_binary(&QueryAnswer::ValidationRules {
+            rules: config_read(&deps.storage).load()?.validation,
+        }),
+        QueryMsg::PrivateMetadata {
+            token_id,
+            viewer,
+            block,
+        } => query_private_metadata(deps, token_id, viewer, block),
+        QueryMsg::IsSignedBy {
+            cid,
+            required,
+            threshold,
+            claim,
+        } => is_signed_by(deps, cid, required, threshold, claim),
+    }
+}
+
+fn get_signatures<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    cid: String,
+) -> StdResult<Binary> {
+    let cid = Cid::from_str(&cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    let signatures = load_signatures(&deps.storage, &cid)?;
+
+    to_binary(&QueryAnswer::GetSignatures { signatures })
+}
+
+fn list_attestations<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    cid: String,
+    start_after: Option<u32>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let cid = Cid::from_str(&cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    let attestations = load_attestations(&deps.storage, &cid, start_after, page_limit(limit))?;
+
+    to_binary(&QueryAnswer::ListAttestations { attestations })
+}
+
+fn page_limit(limit: Option<u32>) -> u32 {
+    limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT)
+}
+
+fn get_credential<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    cid: String,
+) -> StdResult<Binary> {
+    let cid = Cid::from_str(&cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    let mut credential = may_load_credential(&deps.storage, &cid)?;
+    if let Some(record) = &mut credential {
+        if let Some(entry) = &record.status_list {
+            record.status = credential_state(&deps.storage, entry)?;
+        }
+    }
+
+    to_binary(&QueryAnswer::GetCredential { credential })
+}
+
+fn resolve_did<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    did: String,
+) -> StdResult<Binary> {
+    let record = may_load_did(&deps.storage, &did)?
+        .ok_or_else(|| StdError::not_found(format!("DID {}", did)))?;
+    let document = to_document(&deps.api, &did, &record)?;
+
+    to_binary(&QueryAnswer::ResolveDid { document })
+}
+
+fn credential_status<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    issuer: String,
+    list_id: String,
+    index: u32,
+) -> StdResult<Binary> {
+    check_index(index)?;
+    let list = may_load_status_list(&deps.storage, &issuer, &list_id)?
+        .ok_or_else(|| StdError::not_found(format!("Status list {}", list_id)))?;
+
+    to_binary(&QueryAnswer::CredentialStatus {
+        revoked: is_set(&list.bits, index),
+    })
+}
+
+fn status_list<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    issuer: String,
+    list_id: String,
+) -> StdResult<Binary> {
+    let list = may_load_status_list(&deps.storage, &issuer, &list_id)?
+        .ok_or_else(|| StdError::not_found(format!("Status list {}", list_id)))?;
+
+    to_binary(&QueryAnswer::StatusList {
+        purpose: list.purpose,
+        encoded_list: encode_list(&list.bits)?,
+    })
+}
+
+fn swap_status<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    swap_id: String,
+) -> StdResult<Binary> {
+    let hashlock = hex::decode(&swap_id).map_err(|e| StdError::parse_err("swap_id", e))?;
+    let swap = may_load_swap(&deps.storage, &hashlock)?
+        .ok_or_else(|| StdError::not_found(format!("Swap {}", swap_id)))?;
+
+    to_binary(&QueryAnswer::SwapStatus {
+        cid: swap.cid,
+        token_id: swap.token_id,
+        sender: deps.api.human_address(&swap.sender)?,
+        target_chain: swap.target_chain,
+        recipient: swap.recipient,
+        hashlock: swap.hashlock,
+        timeout_height: swap.timeout_height,
+        state: swap.state,
+        preimage: swap.preimage,
+    })
+}
+
+fn get_provenance<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    cid: String,
+) -> StdResult<Binary> {
+    let cid = Cid::from_str(&cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    let provenance = may_load_provenance(&deps.storage, &cid)?;
+
+    to_binary(&QueryAnswer::GetProvenance { provenance })
+}
+
+/// Checks a viewing key and returns the viewer's canonical address
+fn authenticate_viewer<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    viewer: &ViewerInfo,
+) -> StdResult<CanonicalAddr> {
+    let address = deps.api.canonical_address(&viewer.address)?;
+    match may_load_viewing_key(&deps.storage, &address)? {
+        Some(hash) if check_viewing_key(&hash, &viewer.viewing_key) => Ok(address),
+        _ => Err(StdError::unauthorized()),
+    }
+}
+
+/// Ownership is public; approvals are only listed to the owner
+fn owner_of<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token_id: String,
+    viewer: Option<ViewerInfo>,
+    include_expired: bool,
+    block: Option<BlockTime>,
+) -> StdResult<Binary> {
+    let nft = load_nft(&deps.storage, &token_id)?;
+
+    let mut approvals = vec![];
+    if let Some(viewer) = viewer {
+        if authenticate_viewer(deps, &viewer)? == nft.owner {
+            for approval in &nft.approvals {
+                let expired = match &block {
+                    Some(block) => approval.expires.is_expired(block),
+                    None => false,
+                };
+                if include_expired || !expired {
+                    approvals.push(Cw721Approval {
+                        spender: deps.api.human_address(&approval.spender)?,
+                        expires: approval.expires.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    to_binary(&QueryAnswer::OwnerOf {
+        owner: deps.api.human_address(&nft.owner)?,
+        approvals,
+    })
+}
+
+fn nft_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token_id: String,
+) -> StdResult<Binary> {
+    let nft = load_nft(&deps.storage, &token_id)?;
+    let cid = Cid::from_str(&nft.metadata_cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    let node = load_block(&deps.storage, &cid)?
+        .decode::<DagCborCodec, Ipld>()
+        .map_err(|e| StdError::parse_err("DAG-CBOR", e))?;
+    let metadata = public_metadata(&node);
+
+    to_binary(&QueryAnswer::NftInfo {
+        token_uri: metadata.token_uri,
+        extension: metadata.extension,
+    })
+}
+
+/// Full metadata, for the owner and addresses allowed to transfer the token
+fn query_private_metadata<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token_id: String,
+    viewer: ViewerInfo,
+    block: Option<BlockTime>,
+) -> StdResult<Binary> {
+    let nft = load_nft(&deps.storage, &token_id)?;
+    let viewer = authenticate_viewer(deps, &viewer)?;
+    let allowed = match block {
+        Some(block) => can_transfer(&deps.storage, &block, &nft, &viewer)?,
+        None => nft.owner == viewer,
+    };
+    if !allowed {
+        return Err(StdError::unauthorized());
+    }
+
+    let cid = Cid::from_str(&nft.metadata_cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    let node = load_block(&deps.storage, &cid)?
+        .decode::<DagCborCodec, Ipld>()
+        .map_err(|e| StdError::parse_err("DAG-CBOR", e))?;
+    let metadata = private_metadata(&nft.metadata_cid, &node);
+
+    to_binary(&QueryAnswer::PrivateMetadata {
+        token_uri: metadata.token_uri,
+        extension: metadata.extension,
+    })
+}
+
+/// Collects the licenses declared by a metadata block and every stored block in its
+/// `parent`/`sources` lineage, with the royalties owed to each payee
+fn license_terms<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    cid: String,
+) -> StdResult<Binary> {
+    let root = Cid::from_str(&cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    if may_load_block_index(&deps.storage, &root)?.is_none() {
+        return Err(StdError::not_found(format!("Block {}", cid)));
+    }
+
+    let mut queue = vec![root];
+    let mut visited: Vec<Cid> = vec![];
+    let mut terms = vec![];
+    while !queue.is_empty() {
+        let next = queue.remove(0);
+        if visited.contains(&next) {
+            continue;
+        }
+        if visited.len() == MAX_LINEAGE {
+            return Err(StdError::generic_err(format!(
+                "Lineage of {} exceeds {} blocks",
+                cid, MAX_LINEAGE
+            )));
+        }
+        visited.push(next);
+
+        // Blocks that are not stored here end the walk on that branch
+        if may_load_block_index(&deps.storage, &next)?.is_none() {
+            continue;
+        }
+        let node = match load_block(&deps.storage, &next)?.decode::<DagCborCodec, Ipld>() {
+            Ok(node) => node,
+            Err(_) => continue,
+        };
+
+        for link in license_links(&node) {
+            if may_load_block_index(&deps.storage, &link)?.is_none() {
+                continue;
+            }
+            let license = load_block(&deps.storage, &link)?
+                .decode::<DagCborCodec, Ipld>()
+                .map_err(|e| StdError::parse_err("DAG-CBOR", e))?;
+            if let Some(term) = decode_license(&next, &license) {
+                terms.push(term);
+            }
+        }
+        queue.extend(lineage_links(&node));
+    }
+
+    to_binary(&QueryAnswer::LicenseTerms {
+        royalties: royalties(&terms),
+        terms,
+    })
+}
+
+/// A block on the path an export is walking, with its links and how many of them were followed
+struct ExportFrame {
+    cid: Cid,
+    links: Vec<Cid>,
+    next: usize,
+}
+
+/// Links of a block reached at `depth` that the export follows
+fn export_links(
+    cid: &Cid,
+    data: Vec<u8>,
+    depth: u32,
+    max_depth: Option<u32>,
+) -> StdResult<Vec<Cid>> {
+    let mut links = vec![];
+    if max_depth.map_or(true, |max| depth < max) {
+        IpldBlock::new(*cid, data)
+            .and_then(|block| block.references(&mut links))
+            .map_err(|e| StdError::parse_err(codec_name(cid.codec()), e))?;
+    }
+    Ok(links)
+}
+
+/// Rebuilds the walk of a cursor, checking that each block on its path is a link of the one before,
+/// starting from `root`
+fn resume_export<S: ReadonlyStorage>(
+    storage: &S,
+    root: &Cid,
+    max_depth: Option<u32>,
+    cursor: &Binary,
+) -> StdResult<Vec<ExportFrame>> {
+    let foreign = || StdError::generic_err(format!("Export cursor does not belong to {}", root));
+    let mut path: Vec<ExportFrame> = vec![];
+    for (depth, (cid, next)) in decode_cursor(cursor.as_slice())?.into_iter().enumerate() {
+        let expected = match path.last() {
+            None => Some(root),
+            Some(parent) => parent.next.checked_sub(1).and_then(|i| parent.links.get(i)),
+        };
+        if expected != Some(&cid) {
+            return Err(foreign());
+        }
+        let data = may_load_block_data(storage, &cid).ok_or_else(foreign)?;
+        let links = export_links(&cid, data, depth as u32, max_depth)?;
+        let next = next as usize;
+        if next > links.len() {
+            return Err(foreign());
+        }
+        path.push(ExportFrame { cid, links, next });
+    }
+    Ok(path)
+}
+
+/// Walks the DAG under `root` depth first and returns a page of its CARv1 encoding. The first page
+/// starts with the header and the pages concatenate into one archive. `cursor` is the path from the root
+/// to where the last page stopped, so it grows with the depth of the DAG rather than its size. Blocks are
+/// unique within a page, but a block linked from several places can appear again on a later page. Links to
+/// blocks that are not stored here are left out.
+fn export_car<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    root: String,
+    max_depth: Option<u32>,
+    max_bytes: Option<u64>,
+    cursor: Option<Binary>,
+) -> StdResult<Binary> {
+    let root = Cid::from_str(&root).map_err(|e| StdError::parse_err("Cid", e))?;
+    let data = may_load_block_data(&deps.storage, &root)
+        .ok_or_else(|| StdError::not_found(format!("Block {}", root)))?;
+    let max_bytes = max_bytes.unwrap_or(DEFAULT_EXPORT_BYTES);
+
+    let mut car = vec![];
+    let mut blocks = 0;
+    let mut path = match &cursor {
+        Some(cursor) => resume_export(&deps.storage, &root, max_depth, cursor)?,
+        None => {
+            write_header(&mut car, &[root])?;
+            write_block(&mut car, &root, &data);
+            blocks += 1;
+            let links = export_links(&root, data, 0, max_depth)?;
+            vec![ExportFrame {
+                cid: root,
+                links,
+                next: 0,
+            }]
+        }
+    };
+    // the shallowest depth each block was reached at on this page
+    let mut visited: BTreeMap<Cid, u32> = BTreeMap::new();
+    let mut next = None;
+    loop {
+        let depth = path.len() as u32;
+        let frame = match path.last_mut() {
+            Some(frame) => frame,
+            None => break,
+        };
+        let cid = match frame.links.get(frame.next) {
+            Some(cid) => *cid,
+            None => {
+                path.pop();
+                continue;
+            }
+        };
+        frame.next += 1;
+
+        let first_visit = match visited.get(&cid) {
+            Some(seen_depth) if *seen_depth <= depth => continue,
+            // reached closer to the root: walk its links again, but emit it once
+            Some(_) => false,
+            None => true,
+        };
+        let data = match may_load_block_data(&deps.storage, &cid) {
+            Some(data) => data,
+            None => continue,
+        };
+        if first_visit {
+            let mut section = vec![];
+            write_block(&mut section, &cid, &data);
+            // every page holds at least one block
+            if blocks > 0 && (car.len() + section.len()) as u64 > max_bytes {
+                frame.next -= 1;
+                let position: Vec<_> = path
+                    .iter()
+                    .map(|frame| (frame.cid, frame.next as u32))
+                    .collect();
+                next = Some(Binary::from(encode_cursor(&position)?));
+                break;
+            }
+            car.extend(section);
+            blocks += 1;
+        }
+        visited.insert(cid, depth);
+        let links = export_links(&cid, data, depth, max_depth)?;
+        path.push(ExportFrame {
+            cid,
+            links,
+            next: 0,
+        });
+    }
+
+    to_binary(&QueryAnswer::ExportCar {
+        car: Binary::from(car),
+        cursor: next,
+    })
+}
+
+/// Fee for a write of `bytes`, one entry per accepted denom
+fn estimate_fee<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    ops: u64,
+    bytes: u64,
+) -> StdResult<Binary> {
+    let state = config_read(&deps.storage).load()?;
+
+    to_binary(&QueryAnswer::EstimateFee {
+        fees: quote_fees(&state.fees, ops, bytes),
+    })
+}
+
+fn usage<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+) -> StdResult<Binary> {
+    let state = config_read(&deps.storage).load()?;
+    let usage = load_usage(&deps.storage, &deps.api.canonical_address(&address)?)?;
+
+    to_binary(&QueryAnswer::Usage {
+        bytes_used: usage.bytes,
+        block_count: usage.blocks,
+        remaining_bytes: remaining_bytes(&state.quotas, &usage),
+    })
+}
+
+fn is_signed_by<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    cid: String,
+    required: Vec<Binary>,
+    threshold: u32,
+    claim: Option<String>,
+) -> StdResult<Binary> {
+    let cid = Cid::from_str(&cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    if threshold == 0 {
+        return Err(StdError::generic_err("threshold must be at least 1"));
+    }
+
+    // Author signatures make no claim, so they only count when no claim is asked for
+    let authors: Vec<Binary> = match claim {
+        Some(_) => vec![],
+        None => load_signatures(&deps.storage, &cid)?
+            .into_iter()
+            .map(|s| s.pubkey)
+            .collect(),
+    };
+    let mut count = 0;
+    for (i, key) in required.iter().enumerate() {
+        if required[..i].contains(key) {
+            continue;
+        }
+        let attested = may_load_attested_claim(&deps.storage, &cid, key.as_slice())?;
+        let signed = match (&claim, attested) {
+            (Some(claim), Some(attested)) => *claim == attested,
+            (Some(_), None) => false,
+            (None, attested) => attested.is_some() || authors.contains(key),
+        };
+        if signed {
+            count += 1;
+        }
+    }
+
+    to_binary(&QueryAnswer::IsSignedBy {
+        signed: count >= threshold,
+        count,
+    })
+}
+
+fn get_metadata<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    cid: String,
+    path: String,
+    format: DocumentFormat,
+) -> StdResult<Binary> {
+    let block = load_path_block(&deps.storage, PREFIX_METADATA, &cid, &path)?;
+    let data = match format {
+        DocumentFormat::Raw => Document::Raw(Binary::from(block.data())),
+        DocumentFormat::DagJson => Document::DagJson(to_dag_json(&block)?),
+        DocumentFormat::Typed => {
+            let node = block
+                .decode::<DagCborCodec, Ipld>()
+                .map_err(|e| StdError::parse_err("DAG-CBOR", e))?;
+            let licenses = match &node {
+                Ipld::Map(map) if map.contains_key("licenses") => {
+                    Some(load_licenses(&deps.storage, block.cid(), &node)?)
+                }
+                _ => None,
+            };
+            Document::Typed(Metadata {
+                cid: block.cid().to_string(),
+                path,
+                data: decode_metadata(&node, licenses)?,
+            })
+        }
+    };
+
+    to_binary(&QueryAnswer::GetMetadata { data })
+}
+
+/// The licenses a metadata block links to, in order
+fn load_licenses<S: ReadonlyStorage>(
+    storage: &S,
+    cid: &Cid,
+    node: &Ipld,
+) -> StdResult<Vec<SourceLicense>> {
+    license_links(node)
+        .iter()
+        .map(|link| {
+            let license = load_block(storage, link)?
+                .decode::<DagCborCodec, Ipld>()
+                .map_err(|e| StdError::parse_err("DAG-CBOR", e))?;
+            let term = decode_license(cid, &license)
+                .ok_or_else(|| StdError::generic_err(format!("{} is not a license", link)))?;
+            Ok(SourceLicense {
+                source: term.source,
+                license: term.license,
+                royalty_bps: term.royalty_bps,
+                payee: term.payee,
+            })
+        })
+        .collect()
+}
+
+fn get_file<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    cid: String,
+    path: String,
+    format: DocumentFormat,
+) -> StdResult<Binary> {
+    let block = load_path_block(&deps.storage, PREFIX_FILES, &cid, &path)?;
+    let data = match format {
+        DocumentFormat::Raw => Document::Raw(Binary::from(block.data())),
+        DocumentFormat::DagJson => Document::DagJson(to_dag_json(&block)?),
+        DocumentFormat::Typed => {
+            // UnixFS files carry only their content
+            if block.cid().codec() != DAG_CBOR {
+                return Err(StdError::generic_err(
+                    "Only DAG-CBOR files can be returned typed",
+                ));
+            }
+            let node = block
+                .decode::<DagCborCodec, Ipld>()
+                .map_err(|e| StdError::parse_err("DAG-CBOR", e))?;
+            Document::Typed(decode_file(block.cid(), &node)?)
+        }
+    };
+
+    to_binary(&QueryAnswer::GetFile { data })
+}
+
+/// Raw bytes of any stored block, whatever its codec or path
+fn get_block<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    cid: String,
+) -> StdResult<Binary> {
+    let cid = Cid::from_str(&cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    let block = load_block(&deps.storage, &cid)?;
+
+    to_binary(&QueryAnswer::GetBlock {
+        data: Binary::from(block.data()),
+    })
+}
+
+fn list_schemas<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<u32>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let schemas = load_schemas(&deps.storage, start_after, page_limit(limit))?
+        .into_iter()
+        .map(|entry| {
+            Ok(SchemaInfo {
+                name: entry.name,
+                cid: entry.cid,
+                owner: deps.api.human_address(&entry.owner)?,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_binary(&QueryAnswer::ListSchemas { schemas })
+}
+
+/// Loads a block only if it was written under `path` in `namespace`
+fn load_path_block<S: ReadonlyStorage>(
+    storage: &S,
+    namespace: &[u8],
+    cid: &str,
+    path: &str,
+) -> StdResult<IpldBlock> {
+    let cid = Cid::from_str(cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    if !has_path(storage, namespace, &cid, path)? {
+        return Err(StdError::not_found(format!("{} at {}", cid, path)));
+    }
+    load_block(storage, &cid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::car::encode_car;
+    use crate::msg::LegacyKind;
+    use crate::quota::QuotaError;
+    use crate::state::{SignatureAlgo, SourceLicense};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coin, coins, from_slice, Uint128};
+    use libipld::raw::RawCodec;
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+
+        let msg = InitMsg { tag: "test".to_string() };
+        let env = mock_env("creator", &coins(1000, "xdv"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = init(&mut deps, env, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+    }
+
+    #[test]
+    fn add_metadata() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+
+        let amount = coins(40, "ETH");
+        let collateral = coins(1, "BTC");
+        let expires = 100_000;
+        let msg = InitMsg { tag: "test".to_string() };
+        let env = mock_env("creator", &collateral);
+
+        // we can just call .unwrap() to assert this was a success
+        let _ = init(&mut deps, env, msg).unwrap();
+
+        let data = MetadataSchema {
+            name: "XDV metadata sample: NFT".to_string(),
+            description: "testing sample".to_string(),
+            image:
+                "https://explore.ipld.io/#/explore/QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D"
+                    .to_string(),
+            sources: vec!["QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string()],
+            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            refs: vec![
+                "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+                "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            ],
+            owner: None,
+            verified_credential: None,
+            licenses: None,
+            schema: None,
+        };
+        let cid = "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string();
+        // add metadata
+
+        // add metadata - success message
+        let payload = HandleMsg::AddMetadata {
+            data: data,
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        let resp: HandleResponse =
+            handle(&mut deps, mock_env("creator", &collateral), payload).unwrap();
+
+        let b = resp.data.unwrap_or_default();
+        let object = from_binary(&b).unwrap();
+        match object {
+            HandleAnswer::AddFile { cid } => {}
+            HandleAnswer::AddMetadata { cid } => {
+                assert_eq!(
+                    cid,
+                    "bafyreicnuvbp2lhmanra7r5o564fo4n5hhynqmwqv5l3ymz27gqbmlf2xa"
+                );
+                assert_ne!(
+                    cid,
+                    "hhynqmwqv5l3ymz27gqbmlf2xabafyreicnuvbp2lhmanra7r5o564fo4n5"
+                );
+                assert_ne!(
+                    cid,
+                    "57576"
+                );
+                assert_ne!(
+                    cid,
+                    ""
+                );
+                assert_ne!(
+                    cid,
+                    "0"
+                );
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn add_metadata_baseline_message() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        // clients written before the optional fields existed leave them all out
+        let payload: HandleMsg = from_slice(
+            br#"{"add_metadata":{"path":"/","data":{
+                "name":"XDV metadata sample: NFT",
+                "description":"testing sample",
+                "image":"https://explore.ipld.io/#/explore/QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D",
+                "sources":["QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D"],
+                "parent":"QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D",
+                "refs":["QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D","QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D"]
+            }}}"#,
+        )
+        .unwrap();
+        match &payload {
+            HandleMsg::AddMetadata {
+                data, signature, ..
+            } => {
+                assert_eq!(data.owner, None);
+                assert_eq!(data.verified_credential, None);
+                assert_eq!(data.licenses, None);
+                assert_eq!(data.schema, None);
+                assert_eq!(*signature, None);
+            }
+            _ => panic!("unexpected message"),
+        }
+
+        let resp = handle(&mut deps, mock_env("creator", &[]), payload).unwrap();
+        match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::AddMetadata { cid } => assert_eq!(
+                cid,
+                "bafyreicnuvbp2lhmanra7r5o564fo4n5hhynqmwqv5l3ymz27gqbmlf2xa"
+            ),
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    // fn add_file() {
+    //     let mut deps = mock_dependencies(20, &coins(2, "token"));
+
+    //     let amount = coins(40, "ETH");
+    //     let collateral = coins(1, "BTC");
+    //     let expires = 100_000;
+    //     let msg = InitMsg {};
+    //     let env = mock_env("creator", &collateral);
+
+    //     // we can just call .unwrap() to assert this was a success
+    //     let _ = init(&mut deps, env, msg).unwrap();
+
+    //     let data = MetadataSchema {
+            
+    //     };
+    //     let cid = "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string();
+    //     // add metadata
+
+    //     // add metadata - success message
+    //     let payload = HandleMsg::AddFile {
+    //         data: data,
+    //         path: "/".to_string(),
+    //     };
+    //     let resp: HandleResponse =
+    //         handle(&mut deps, mock_env("creator", &collateral), payload).unwrap();
+
+    //     let b = resp.data.unwrap_or_default();
+    //     let object = from_binary(&b).unwrap();
+    //     match object {
+    //         HandleAnswer::AddFile { cid } => {
+    //             assert_eq!(
+    //             cid,
+    //             "bafyreicnuvbp2lhmanra7r5o564fo4n5hhynqmwqv5l3ymz27gqbmlf2xa"
+    //         );
+    //     }}
+    //         HandleAnswer::AddMetadata { cid } => {
+                
+    //     }
+    // }
+    #[test]
+    fn get_metadata() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
 
         let amount = coins(40, "ETH");
         let collateral = coins(1, "BTC");
         let expires = 100_000;
         let msg = InitMsg { tag: "test".to_string() };
-        let env = mock_env("creator", &collateral);
+        let env = mock_env("creator", &collateral);
+
+        // we can just call .unwrap() to assert this was a success
+        let _ = init(&mut deps, env, msg).unwrap();
+        
+        //Adding Metadata for the test
+        let data_payload = MetadataSchema {
+            name: "XDV metadata sample: NFT".to_string(),
+            description: "testing sample".to_string(),
+            image:
+                "https://explore.ipld.io/#/explore/QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D"
+                    .to_string(),
+            sources: vec!["QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string()],
+            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            refs: vec![
+                "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+                "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            ],
+            owner: None,
+            verified_credential: None,
+            licenses: None,
+            schema: None,
+        };
+        let cid = "bafyreicnuvbp2lhmanra7r5o564fo4n5hhynqmwqv5l3ymz27gqbmlf2xa".to_string();
+        // add metadata
+
+        // add metadata - success message
+        let payload_m = HandleMsg::AddMetadata {
+            data: data_payload,
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+
+        let resp: HandleResponse =
+            handle(&mut deps, mock_env("creator", &collateral), payload_m).unwrap();
+
+        // get metadata
+        
+        // get metadata - success message
+        let payload_q = QueryMsg::GetMetadata {
+            cid: cid,
+            path: "/".to_string(),
+            format: DocumentFormat::Raw,
+        };
+        let resp: Binary =
+            query(&mut deps, payload_q).unwrap();
+
+        let object = from_binary(&resp).unwrap();
+        match object {
+            QueryAnswer::GetMetadata { data: Document::Raw(data) } => {
+                assert_eq!(
+                    data.len(),
+                    325,
+                );
+                
+            }
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn add_metadata_signed() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let collateral = coins(1, "BTC");
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &collateral), msg).unwrap();
+
+        let data = MetadataSchema {
+            name: "XDV metadata sample: NFT".to_string(),
+            description: "testing sample".to_string(),
+            image: "https://explore.ipld.io/#/explore/QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D"
+                .to_string(),
+            sources: vec!["QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string()],
+            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            refs: vec![],
+            owner: None,
+            verified_credential: None,
+            licenses: None,
+            schema: None,
+        };
+        let block = encode_metadata(&data).unwrap();
+        let signing_key = ed25519_zebra::SigningKey::from([1u8; 32]);
+        let pubkey: [u8; 32] = ed25519_zebra::VerificationKey::from(&signing_key).into();
+        let sig: [u8; 64] = signing_key.sign(block.data()).into();
+
+        // signature over other bytes is rejected
+        let bad_sig: [u8; 64] = signing_key.sign(b"something else").into();
+        let payload = HandleMsg::AddMetadata {
+            data: data.clone(),
+            path: "/".to_string(),
+            signature: Some(Signature {
+                pubkey: Binary::from(&pubkey[..]),
+                algo: SignatureAlgo::Ed25519,
+                sig: Binary::from(&bad_sig[..]),
+            }),
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        assert!(handle(&mut deps, mock_env("creator", &collateral), payload).is_err());
+
+        let signature = Signature {
+            pubkey: Binary::from(&pubkey[..]),
+            algo: SignatureAlgo::Ed25519,
+            sig: Binary::from(&sig[..]),
+        };
+        let payload = HandleMsg::AddMetadata {
+            data,
+            path: "/".to_string(),
+            signature: Some(signature.clone()),
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        let _ = handle(&mut deps, mock_env("creator", &collateral), payload.clone()).unwrap();
+        // writing the same signed document again does not repeat the signature
+        let _ = handle(&mut deps, mock_env("creator", &collateral), payload).unwrap();
+
+        let index = may_load_block_index(&deps.storage, block.cid()).unwrap().unwrap();
+        assert_eq!(index.signer, Some(Binary::from(&pubkey[..])));
+
+        let payload_q = QueryMsg::GetSignatures {
+            cid: block.cid().to_string(),
+        };
+        let resp = query(&deps, payload_q).unwrap();
+        match from_binary(&resp).unwrap() {
+            QueryAnswer::GetSignatures { signatures } => assert_eq!(signatures, vec![signature]),
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn attest_threshold() {
+        use k256::ecdsa::signature::Signer;
+
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let collateral = coins(1, "BTC");
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &collateral), msg).unwrap();
+
+        let data = MetadataSchema {
+            name: "Contract".to_string(),
+            description: "needs two signers".to_string(),
+            image: "".to_string(),
+            sources: vec![],
+            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            refs: vec![],
+            owner: None,
+            verified_credential: None,
+            licenses: None,
+            schema: None,
+        };
+        let cid = *encode_metadata(&data).unwrap().cid();
+        let claim = "approved".to_string();
+        let payload = attestation_payload(&cid, &claim).unwrap();
+
+        // attesting a CID that was never stored fails
+        let issuer = k256::ecdsa::SigningKey::from_bytes(&[3u8; 32]).unwrap();
+        let issuer_pubkey = Binary::from(issuer.verifying_key().to_bytes().as_slice());
+        let issuer_sig: k256::ecdsa::Signature = issuer.sign(&payload);
+        let attest_msg = HandleMsg::Attest {
+            cid: cid.to_string(),
+            pubkey: issuer_pubkey.clone(),
+            algo: SignatureAlgo::Secp256k1,
+            signature: Binary::from(issuer_sig.as_ref()),
+            claim: claim.clone(),
+        };
+        assert!(handle(&mut deps, mock_env("issuer", &[]), attest_msg.clone()).is_err());
+
+        let add_msg = HandleMsg::AddMetadata {
+            data,
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        let _ = handle(&mut deps, mock_env("creator", &collateral), add_msg).unwrap();
+        let _ = handle(&mut deps, mock_env("issuer", &[]), attest_msg).unwrap();
+
+        let delegate = ed25519_zebra::SigningKey::from([4u8; 32]);
+        let delegate_pubkey: [u8; 32] = ed25519_zebra::VerificationKey::from(&delegate).into();
+        let delegate_sig: [u8; 64] = delegate.sign(&payload).into();
+        let attest_msg = HandleMsg::Attest {
+            cid: cid.to_string(),
+            pubkey: Binary::from(&delegate_pubkey[..]),
+            algo: SignatureAlgo::Ed25519,
+            signature: Binary::from(&delegate_sig[..]),
+            claim: "tampered".to_string(),
+        };
+        assert!(handle(&mut deps, mock_env("delegate", &[]), attest_msg).is_err());
+        let attest_msg = HandleMsg::Attest {
+            cid: cid.to_string(),
+            pubkey: Binary::from(&delegate_pubkey[..]),
+            algo: SignatureAlgo::Ed25519,
+            signature: Binary::from(&delegate_sig[..]),
+            claim,
+        };
+        let _ = handle(&mut deps, mock_env("delegate", &[]), attest_msg.clone()).unwrap();
+        // a key attests a CID once
+        assert!(handle(&mut deps, mock_env("delegate", &[]), attest_msg).is_err());
+
+        let list = |start_after: Option<u32>| QueryMsg::ListAttestations {
+            cid: cid.to_string(),
+            start_after,
+            limit: Some(1),
+        };
+        match from_binary(&query(&deps, list(None)).unwrap()).unwrap() {
+            QueryAnswer::ListAttestations { attestations } => {
+                assert_eq!(attestations.len(), 1);
+                assert_eq!(attestations[0].pubkey, issuer_pubkey);
+            }
+            _ => panic!("unexpected answer"),
+        }
+        match from_binary(&query(&deps, list(Some(0))).unwrap()).unwrap() {
+            QueryAnswer::ListAttestations { attestations } => {
+                assert_eq!(attestations[0].pubkey, Binary::from(&delegate_pubkey[..]))
+            }
+            _ => panic!("unexpected answer"),
+        }
+        match from_binary(&query(&deps, list(Some(1))).unwrap()).unwrap() {
+            QueryAnswer::ListAttestations { attestations } => assert!(attestations.is_empty()),
+            _ => panic!("unexpected answer"),
+        }
+
+        let required = vec![
+            issuer_pubkey.clone(),
+            Binary::from(&delegate_pubkey[..]),
+            Binary::from(&[2u8; 33][..]),
+        ];
+        let resp = query(
+            &deps,
+            QueryMsg::IsSignedBy {
+                cid: cid.to_string(),
+                required: required.clone(),
+                threshold: 2,
+                claim: None,
+            },
+        )
+        .unwrap();
+        match from_binary(&resp).unwrap() {
+            QueryAnswer::IsSignedBy { signed, count } => {
+                assert!(signed);
+                assert_eq!(count, 2);
+            }
+            _ => panic!("unexpected answer"),
+        }
+
+        let resp = query(
+            &deps,
+            QueryMsg::IsSignedBy {
+                cid: cid.to_string(),
+                required: required.clone(),
+                threshold: 3,
+                claim: None,
+            },
+        )
+        .unwrap();
+        match from_binary(&resp).unwrap() {
+            QueryAnswer::IsSignedBy { signed, .. } => assert!(!signed),
+            _ => panic!("unexpected answer"),
+        }
+
+        let signed_for = |claim: &str, threshold: u32| QueryMsg::IsSignedBy {
+            cid: cid.to_string(),
+            required: required.clone(),
+            threshold,
+            claim: Some(claim.to_string()),
+        };
+        match from_binary(&query(&deps, signed_for("approved", 2)).unwrap()).unwrap() {
+            QueryAnswer::IsSignedBy { signed, .. } => assert!(signed),
+            _ => panic!("unexpected answer"),
+        }
+        match from_binary(&query(&deps, signed_for("rejected", 1)).unwrap()).unwrap() {
+            QueryAnswer::IsSignedBy { count, .. } => assert_eq!(count, 0),
+            _ => panic!("unexpected answer"),
+        }
+        assert!(query(&deps, signed_for("approved", 0)).is_err());
+    }
+
+    #[test]
+    fn add_metadata_with_credential() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let collateral = coins(1, "BTC");
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &collateral), msg).unwrap();
+        for did in &["did:xdv:holder", "did:xdv:someone"] {
+            let register = HandleMsg::RegisterDid {
+                did: did.to_string(),
+                controllers: None,
+            };
+            let _ = handle(&mut deps, mock_env("creator", &[]), register).unwrap();
+        }
+
+        let issuer = k256::ecdsa::SigningKey::from_bytes(&[5u8; 32]).unwrap();
+        let issuer_key = Binary::from(issuer.verifying_key().to_bytes().as_slice());
+        let now = mock_env("creator", &[]).block.time;
+        let token = crate::vc::tests::issue_es256k(
+            &issuer,
+            &format!(
+                r#"{{"iss":"https://issuer.example","jti":"urn:uuid:1","nbf":{},"exp":{},"vc":{{"credentialSubject":{{"id":"did:xdv:holder"}}}}}}"#,
+                now - 10,
+                now + 1000
+            ),
+        );
+        let data = MetadataSchema {
+            name: "Credentialed".to_string(),
+            description: "testing sample".to_string(),
+            image: "".to_string(),
+            sources: vec![],
+            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            refs: vec![],
+            owner: Some("did:xdv:holder".to_string()),
+            verified_credential: Some(token),
+            licenses: None,
+            schema: None,
+        };
+
+        // owner must be the credential subject
+        let mut wrong_owner = data.clone();
+        wrong_owner.owner = Some("did:xdv:someone".to_string());
+        let payload = HandleMsg::AddMetadata {
+            data: wrong_owner,
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: Some(issuer_key.clone()),
+            private: false,
+            link_policy: None,
+        };
+        assert!(handle(&mut deps, mock_env("creator", &collateral), payload).is_err());
+
+        // and the credential must be signed by the issuer key
+        let payload = HandleMsg::AddMetadata {
+            data: data.clone(),
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: Some(Binary::from(&[2u8; 33][..])),
+            private: false,
+            link_policy: None,
+        };
+        assert!(handle(&mut deps, mock_env("creator", &collateral), payload).is_err());
+
+        let payload = HandleMsg::AddMetadata {
+            data: data.clone(),
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: Some(issuer_key.clone()),
+            private: false,
+            link_policy: None,
+        };
+        let _ = handle(&mut deps, mock_env("creator", &collateral), payload).unwrap();
+
+        // a key cannot vouch for a DID issuer
+        let forged = crate::vc::tests::issue_es256k(
+            &issuer,
+            r#"{"iss":"did:xdv:holder","vc":{"credentialSubject":{"id":"did:xdv:holder"}}}"#,
+        );
+        let payload = HandleMsg::AddMetadata {
+            data: MetadataSchema {
+                verified_credential: Some(forged),
+                ..data.clone()
+            },
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: Some(issuer_key.clone()),
+            private: false,
+            link_policy: None,
+        };
+        assert!(handle(&mut deps, mock_env("creator", &collateral), payload).is_err());
+
+        let cid = encode_metadata(&data).unwrap().cid().to_string();
+        let resp = query(&deps, QueryMsg::GetCredential { cid }).unwrap();
+        match from_binary(&resp).unwrap() {
+            QueryAnswer::GetCredential { credential } => {
+                let credential = credential.unwrap();
+                assert_eq!(credential.issuer, key_fingerprint(issuer_key.as_slice()));
+                assert_eq!(credential.subject, "did:xdv:holder");
+                assert_eq!(credential.status, CredentialState::Verified);
+            }
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn did_registry() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let did = "did:xdv:issuer".to_string();
+        let register = HandleMsg::RegisterDid {
+            did: did.clone(),
+            controllers: Some(vec![HumanAddr::from("alice"), HumanAddr::from("bob")]),
+        };
+        // the sender must be one of the controllers
+        assert!(handle(&mut deps, mock_env("carol", &[]), register.clone()).is_err());
+        let _ = handle(&mut deps, mock_env("alice", &[]), register.clone()).unwrap();
+        assert!(handle(&mut deps, mock_env("alice", &[]), register).is_err());
+
+        let issuer = k256::ecdsa::SigningKey::from_bytes(&[5u8; 32]).unwrap();
+        let add_key = HandleMsg::AddVerificationMethod {
+            did: did.clone(),
+            id: "key-1".to_string(),
+            algo: SignatureAlgo::Secp256k1,
+            public_key: Binary::from(issuer.verifying_key().to_bytes().as_slice()),
+        };
+        // only controllers may update the document
+        assert!(handle(&mut deps, mock_env("carol", &[]), add_key.clone()).is_err());
+        let _ = handle(&mut deps, mock_env("bob", &[]), add_key).unwrap();
+
+        let add_key = HandleMsg::AddVerificationMethod {
+            did: did.clone(),
+            id: "#key-2".to_string(),
+            algo: SignatureAlgo::Ed25519,
+            public_key: Binary::from(&[1u8; 7][..]),
+        };
+        assert!(handle(&mut deps, mock_env("bob", &[]), add_key).is_err());
+
+        let add_service = HandleMsg::AddService {
+            did: did.clone(),
+            id: "hub".to_string(),
+            service_type: "LinkedDomains".to_string(),
+            endpoint: "https://xdv.digital".to_string(),
+        };
+        let _ = handle(&mut deps, mock_env("alice", &[]), add_service).unwrap();
+
+        let resp = query(&deps, QueryMsg::ResolveDid { did: did.clone() }).unwrap();
+        let json = String::from_utf8(resp.as_slice().to_vec()).unwrap();
+        assert!(json.contains("\"@context\""));
+        assert!(json.contains("\"verificationMethod\""));
+        assert!(json.contains("\"serviceEndpoint\""));
+        match from_binary(&resp).unwrap() {
+            QueryAnswer::ResolveDid { document } => {
+                assert_eq!(document.id, did);
+                assert_eq!(document.controller.len(), 2);
+                assert_eq!(document.authentication, vec!["did:xdv:issuer#key-1".to_string()]);
+                assert_eq!(
+                    document.verification_method[0].method_type,
+                    "EcdsaSecp256k1VerificationKey2019"
+                );
+                assert_eq!(document.service[0].id, "did:xdv:issuer#hub");
+            }
+            _ => panic!("unexpected answer"),
+        }
+
+        // credentials issued by the DID verify without an explicit issuer key
+        let register = HandleMsg::RegisterDid {
+            did: "did:xdv:holder".to_string(),
+            controllers: None,
+        };
+        let _ = handle(&mut deps, mock_env("dave", &[]), register).unwrap();
+        let token = crate::vc::tests::issue_es256k(
+            &issuer,
+            r#"{"iss":"did:xdv:issuer","vc":{"credentialSubject":{"id":"did:xdv:holder"}}}"#,
+        );
+        let data = MetadataSchema {
+            name: "Resolved issuer".to_string(),
+            description: "testing sample".to_string(),
+            image: "".to_string(),
+            sources: vec![],
+            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            refs: vec![],
+            owner: Some("did:xdv:holder".to_string()),
+            verified_credential: Some(token),
+            licenses: None,
+            schema: None,
+        };
+        let payload = HandleMsg::AddMetadata {
+            data,
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        // the owner DID is controlled by dave only
+        assert!(handle(&mut deps, mock_env("alice", &[]), payload.clone()).is_err());
+        let _ = handle(&mut deps, mock_env("dave", &[]), payload.clone()).unwrap();
+
+        let remove_key = HandleMsg::RemoveVerificationMethod {
+            did: did.clone(),
+            id: "did:xdv:issuer#key-1".to_string(),
+        };
+        let _ = handle(&mut deps, mock_env("alice", &[]), remove_key.clone()).unwrap();
+        assert!(handle(&mut deps, mock_env("alice", &[]), remove_key).is_err());
+        assert!(handle(&mut deps, mock_env("dave", &[]), payload).is_err());
+
+        let remove_service = HandleMsg::RemoveService {
+            did: did.clone(),
+            id: "#hub".to_string(),
+        };
+        let _ = handle(&mut deps, mock_env("bob", &[]), remove_service.clone()).unwrap();
+        assert!(handle(&mut deps, mock_env("bob", &[]), remove_service).is_err());
+
+        let rotate = |controllers: Vec<HumanAddr>| HandleMsg::SetControllers {
+            did: did.clone(),
+            controllers,
+        };
+        assert!(handle(&mut deps, mock_env("alice", &[]), rotate(vec![])).is_err());
+        let carol = vec![HumanAddr::from("carol")];
+        assert!(handle(&mut deps, mock_env("carol", &[]), rotate(carol.clone())).is_err());
+        let _ = handle(&mut deps, mock_env("alice", &[]), rotate(carol)).unwrap();
+        // the previous controllers lose access
+        let rotate_back = rotate(vec![HumanAddr::from("alice")]);
+        assert!(handle(&mut deps, mock_env("alice", &[]), rotate_back.clone()).is_err());
+        let _ = handle(&mut deps, mock_env("carol", &[]), rotate_back).unwrap();
+    }
+
+    #[test]
+    fn revoked_credentials() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let did = "did:xdv:issuer".to_string();
+        let register = HandleMsg::RegisterDid {
+            did: did.clone(),
+            controllers: None,
+        };
+        let _ = handle(&mut deps, mock_env("issuer", &[]), register).unwrap();
+        let issuer = k256::ecdsa::SigningKey::from_bytes(&[5u8; 32]).unwrap();
+        let add_key = HandleMsg::AddVerificationMethod {
+            did: did.clone(),
+            id: "key-1".to_string(),
+            algo: SignatureAlgo::Secp256k1,
+            public_key: Binary::from(issuer.verifying_key().to_bytes().as_slice()),
+        };
+        let _ = handle(&mut deps, mock_env("issuer", &[]), add_key).unwrap();
+
+        let list_id = "https://xdv.digital/status/1".to_string();
+        let set = |list_id: &str, index: u32, revoked: bool, purpose: StatusPurpose| {
+            HandleMsg::SetRevoked {
+                issuer: did.clone(),
+                list_id: list_id.to_string(),
+                index,
+                revoked,
+                purpose,
+            }
+        };
+        let revoke =
+            |index: u32, revoked: bool| set(&list_id, index, revoked, StatusPurpose::Revocation);
+        // lists belong to the issuer DID, so only its controllers can create or change them
+        assert!(handle(&mut deps, mock_env("mallory", &[]), revoke(7, false)).is_err());
+        let _ = handle(&mut deps, mock_env("issuer", &[]), revoke(7, false)).unwrap();
+        assert!(handle(&mut deps, mock_env("mallory", &[]), revoke(7, true)).is_err());
+
+        let credential = |purpose: &str, list: &str, index: u32| {
+            crate::vc::tests::issue_es256k(
+                &issuer,
+                &format!(
+                    r#"{{"iss":"did:xdv:issuer","vc":{{"credentialSubject":{{"id":"did:web:holder"}},"credentialStatus":{{"type":"StatusList2021Entry","statusPurpose":"{}","statusListIndex":"{}","statusListCredential":"{}"}}}}}}"#,
+                    purpose, index, list
+                ),
+            )
+        };
+        let metadata = |token: String| MetadataSchema {
+            name: "Revocable".to_string(),
+            description: "testing sample".to_string(),
+            image: "".to_string(),
+            sources: vec![],
+            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            refs: vec![],
+            owner: Some("did:web:holder".to_string()),
+            verified_credential: Some(token),
+            licenses: None,
+            schema: None,
+        };
+        let add = |token: String| HandleMsg::AddMetadata {
+            data: metadata(token),
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+
+        let _ = handle(&mut deps, mock_env("issuer", &[]), revoke(3, true)).unwrap();
+        // revocations are final
+        assert!(handle(&mut deps, mock_env("issuer", &[]), revoke(3, false)).is_err());
+        let revoked = credential("revocation", &list_id, 3);
+        assert!(handle(&mut deps, mock_env("holder", &[]), add(revoked)).is_err());
+        // the list must exist and serve the purpose the credential names
+        let unknown = credential("revocation", "https://xdv.digital/status/2", 4);
+        assert!(handle(&mut deps, mock_env("holder", &[]), add(unknown)).is_err());
+        let wrong_purpose = credential("suspension", &list_id, 4);
+        assert!(handle(&mut deps, mock_env("holder", &[]), add(wrong_purpose)).is_err());
+        let valid = credential("revocation", &list_id, 4);
+        let _ = handle(&mut deps, mock_env("holder", &[]), add(valid)).unwrap();
+
+        // revoking afterwards is reflected in the stored credential
+        let _ = handle(&mut deps, mock_env("issuer", &[]), revoke(4, true)).unwrap();
+        let cid = encode_metadata(&metadata(credential("revocation", &list_id, 4)))
+            .unwrap()
+            .cid()
+            .to_string();
+        match from_binary(&query(&deps, QueryMsg::GetCredential { cid }).unwrap()).unwrap() {
+            QueryAnswer::GetCredential { credential } => {
+                assert_eq!(credential.unwrap().status, CredentialState::Revoked)
+            }
+            _ => panic!("unexpected answer"),
+        }
+
+        // suspensions can be lifted
+        let suspensions = "https://xdv.digital/suspended/1";
+        let suspend = |suspended: bool| set(suspensions, 5, suspended, StatusPurpose::Suspension);
+        let _ = handle(&mut deps, mock_env("issuer", &[]), suspend(true)).unwrap();
+        let suspended = credential("suspension", suspensions, 5);
+        assert!(handle(&mut deps, mock_env("holder", &[]), add(suspended.clone())).is_err());
+        let _ = handle(&mut deps, mock_env("issuer", &[]), suspend(false)).unwrap();
+        let _ = handle(&mut deps, mock_env("holder", &[]), add(suspended)).unwrap();
+
+        let status = QueryMsg::CredentialStatus {
+            issuer: did.clone(),
+            list_id: list_id.clone(),
+            index: 4,
+        };
+        match from_binary(&query(&deps, status).unwrap()).unwrap() {
+            QueryAnswer::CredentialStatus { revoked } => assert!(revoked),
+            _ => panic!("unexpected answer"),
+        }
+        let status_list = QueryMsg::StatusList {
+            issuer: did.clone(),
+            list_id,
+        };
+        match from_binary(&query(&deps, status_list).unwrap()).unwrap() {
+            QueryAnswer::StatusList {
+                purpose,
+                encoded_list,
+            } => {
+                assert_eq!(purpose, StatusPurpose::Revocation);
+                assert!(!encoded_list.is_empty())
+            }
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn swap_lifecycle() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let data = |name: &str| MetadataSchema {
+            name: name.to_string(),
+            description: "testing sample".to_string(),
+            image: "".to_string(),
+            sources: vec![],
+            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            refs: vec![],
+            owner: Some("creator".to_string()),
+            verified_credential: None,
+            licenses: None,
+            schema: None,
+        };
+        let mut cids = vec![];
+        for name in &["claimed", "refunded"] {
+            let payload = HandleMsg::AddMetadata {
+                data: data(name),
+                path: "/".to_string(),
+                signature: None,
+                issuer_key: None,
+                private: false,
+                link_policy: None,
+            };
+            // anyone can write the block first, which gives them no say over the token
+            let _ = handle(&mut deps, mock_env("someone", &[]), payload).unwrap();
+            let cid = encode_metadata(&data(name)).unwrap().cid().to_string();
+            let mint = HandleMsg::MintNft {
+                token_id: name.to_string(),
+                metadata_cid: cid.clone(),
+                owner: None,
+                signature: None,
+            };
+            let _ = handle(&mut deps, mock_env("creator", &[]), mint).unwrap();
+            cids.push(cid);
+        }
+        let owner_of = |deps: &Extern<_, _, _>, token_id: &str| {
+            let owner_of = QueryMsg::OwnerOf {
+                token_id: token_id.to_string(),
+                viewer: None,
+                include_expired: None,
+                block: None,
+            };
+            match from_binary(&query(deps, owner_of).unwrap()).unwrap() {
+                QueryAnswer::OwnerOf { owner, .. } => owner,
+                _ => panic!("unexpected answer"),
+            }
+        };
+
+        let env = mock_env("creator", &[]);
+        let height = env.block.height;
+        let preimage = Binary::from(b"secret one".as_ref());
+        let hashlock = Binary::from(Sha256::digest(preimage.as_slice()).as_slice());
+        let lock = HandleMsg::LockForSwap {
+            cid: cids[0].clone(),
+            target_chain: "bsc".to_string(),
+            recipient: "0x1234".to_string(),
+            hashlock: hashlock.clone(),
+            timeout_height: height + 10,
+        };
+        // only the token owner can lock it, not whoever wrote the block
+        let err = handle(&mut deps, mock_env("someone", &[]), lock.clone()).unwrap_err();
+        assert_eq!(err, StdError::unauthorized());
+        let res = handle(&mut deps, mock_env("creator", &[]), lock).unwrap();
+        let swap_id = hex::encode(hashlock.as_slice());
+        assert!(res.log.contains(&log("swap_id", &swap_id)));
+        assert!(res.log.contains(&log("token_id", "claimed")));
+        assert!(res.log.contains(&log("target_chain", "bsc")));
+
+        // the contract holds a locked token, and it cannot be moved
+        assert_eq!(owner_of(&deps, "claimed"), HumanAddr::from(MOCK_CONTRACT_ADDR));
+        let transfer = HandleMsg::TransferNft {
+            recipient: HumanAddr::from("someone"),
+            token_id: "claimed".to_string(),
+        };
+        let err = handle(&mut deps, mock_env("creator", &[]), transfer.clone()).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!("Token claimed is locked in swap {}", swap_id))
+        );
+        let approve = HandleMsg::Approve {
+            spender: HumanAddr::from("someone"),
+            token_id: "claimed".to_string(),
+            expires: None,
+        };
+        assert!(handle(&mut deps, mock_env("creator", &[]), approve).is_err());
+
+        // a locked CID cannot be locked again
+        let relock = HandleMsg::LockForSwap {
+            cid: cids[0].clone(),
+            target_chain: "eth".to_string(),
+            recipient: "0x1234".to_string(),
+            hashlock: Binary::from(&[9u8; 32][..]),
+            timeout_height: height + 10,
+        };
+        assert!(handle(&mut deps, mock_env("creator", &[]), relock.clone()).is_err());
+
+        let refund = HandleMsg::RefundSwap {
+            swap_id: swap_id.clone(),
+        };
+        assert!(handle(&mut deps, mock_env("creator", &[]), refund).is_err());
+
+        let wrong = HandleMsg::ClaimSwap {
+            preimage: Binary::from(b"guess".as_ref()),
+        };
+        assert!(handle(&mut deps, mock_env("relayer", &[]), wrong).is_err());
+        let claim = HandleMsg::ClaimSwap {
+            preimage: preimage.clone(),
+        };
+        let _ = handle(&mut deps, mock_env("relayer", &[]), claim.clone()).unwrap();
+        assert!(handle(&mut deps, mock_env("relayer", &[]), claim).is_err());
+        assert!(handle(&mut deps, mock_env("creator", &[]), relock).is_err());
+        assert!(handle(&mut deps, mock_env("creator", &[]), transfer).is_err());
+        assert_eq!(owner_of(&deps, "claimed"), HumanAddr::from(MOCK_CONTRACT_ADDR));
+
+        let status = QueryMsg::SwapStatus {
+            swap_id: swap_id.clone(),
+        };
+        match from_binary(&query(&deps, status).unwrap()).unwrap() {
+            QueryAnswer::SwapStatus {
+                state, preimage: p, ..
+            } => {
+                assert_eq!(state, SwapState::Claimed);
+                assert_eq!(p, Some(preimage));
+            }
+            _ => panic!("unexpected answer"),
+        }
+
+        // the second CID is refunded once the timeout passes
+        let hashlock = Binary::from(Sha256::digest(b"secret two").as_slice());
+        let lock = HandleMsg::LockForSwap {
+            cid: cids[1].clone(),
+            target_chain: "bsc".to_string(),
+            recipient: "0x1234".to_string(),
+            hashlock: hashlock.clone(),
+            timeout_height: height + 5,
+        };
+        let _ = handle(&mut deps, mock_env("creator", &[]), lock).unwrap();
+
+        let mut later = mock_env("creator", &[]);
+        later.block.height = height + 5;
+        let claim = HandleMsg::ClaimSwap {
+            preimage: Binary::from(b"secret two".as_ref()),
+        };
+        assert!(handle(&mut deps, later.clone(), claim).is_err());
+        let refund = HandleMsg::RefundSwap {
+            swap_id: hex::encode(hashlock.as_slice()),
+        };
+        assert!(handle(&mut deps, mock_env("someone", &[]), refund.clone()).is_err());
+        let _ = handle(&mut deps, later.clone(), refund).unwrap();
+        assert_eq!(owner_of(&deps, "refunded"), HumanAddr::from("creator"));
+        let transfer = HandleMsg::TransferNft {
+            recipient: HumanAddr::from("someone"),
+            token_id: "refunded".to_string(),
+        };
+        let _ = handle(&mut deps, later, transfer).unwrap();
+
+        let status = QueryMsg::SwapStatus {
+            swap_id: hex::encode(hashlock.as_slice()),
+        };
+        match from_binary(&query(&deps, status).unwrap()).unwrap() {
+            QueryAnswer::SwapStatus { state, .. } => assert_eq!(state, SwapState::Refunded),
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn mint_from_remote_threshold() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let relayer_keys: Vec<ed25519_zebra::SigningKey> = (1u8..4)
+            .map(|i| ed25519_zebra::SigningKey::from([i; 32]))
+            .collect();
+        let pubkey = |key: &ed25519_zebra::SigningKey| {
+            let pubkey: [u8; 32] = ed25519_zebra::VerificationKey::from(key).into();
+            Binary::from(&pubkey[..])
+        };
+        let set = HandleMsg::SetRelayers {
+            keys: relayer_keys
+                .iter()
+                .map(|k| RelayerKey {
+                    pubkey: pubkey(k),
+                    algo: SignatureAlgo::Ed25519,
+                })
+                .collect(),
+            threshold: 2,
+        };
+        assert!(handle(&mut deps, mock_env("someone", &[]), set.clone()).is_err());
+        let _ = handle(&mut deps, mock_env("creator", &[]), set).unwrap();
+
+        let remote = |name: &str| {
+            encode_metadata(&MetadataSchema {
+                name: name.to_string(),
+                description: "minted elsewhere".to_string(),
+                image: "".to_string(),
+                sources: vec![],
+                parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+                refs: vec![],
+                owner: None,
+                verified_credential: None,
+                licenses: None,
+                schema: None,
+            })
+            .unwrap()
+        };
+        let signed_for = |contract: &str,
+                          block: &IpldBlock,
+                          nonce: u64,
+                          signers: &[&ed25519_zebra::SigningKey]| {
+            let proof = MintProof {
+                data: Binary::from(block.data()),
+                tx_hash: "0xabc".to_string(),
+                nonce,
+                recipient: HumanAddr::from("holder"),
+            };
+            let mut env = mock_env("relayer", &[]);
+            env.contract.address = HumanAddr::from(contract);
+            let payload = mint_payload(&env, "bsc", block.cid(), &proof).unwrap();
+            HandleMsg::MintFromRemote {
+                source_chain: "bsc".to_string(),
+                source_cid: block.cid().to_string(),
+                relayer_sig: signers
+                    .iter()
+                    .map(|k| {
+                        let sig: [u8; 64] = k.sign(&payload).into();
+                        Signature {
+                            pubkey: pubkey(k),
+                            algo: SignatureAlgo::Ed25519,
+                            sig: Binary::from(&sig[..]),
+                        }
+                    })
+                    .collect(),
+                proof,
+            }
+        };
+        let mint = |block: &IpldBlock, nonce: u64, signers: &[&ed25519_zebra::SigningKey]| {
+            signed_for(MOCK_CONTRACT_ADDR, block, nonce, signers)
+        };
+
+        let block = remote("first");
+        let env = || mock_env("relayer", &[]);
+        // signatures made for another deployment do not carry over
+        let elsewhere = signed_for("other-contract", &block, 1, &[&relayer_keys[0], &relayer_keys[1]]);
+        assert!(handle(&mut deps, env(), elsewhere).is_err());
+        // one signature, or the same one twice, is below the threshold
+        let below = mint(&block, 1, &[&relayer_keys[0]]);
+        assert!(handle(&mut deps, env(), below).is_err());
+        let twice = mint(&block, 1, &[&relayer_keys[0], &relayer_keys[0]]);
+        assert!(handle(&mut deps, env(), twice).is_err());
+
+        // bytes that do not hash to the CID are rejected
+        let mut tampered = mint(&block, 1, &[&relayer_keys[0], &relayer_keys[1]]);
+        if let HandleMsg::MintFromRemote { proof, .. } = &mut tampered {
+            proof.data = Binary::from(remote("other").data());
+        }
+        assert!(handle(&mut deps, env(), tampered).is_err());
+
+        let ok = mint(&block, 1, &[&relayer_keys[0], &relayer_keys[2]]);
+        let res = handle(&mut deps, env(), ok.clone()).unwrap();
+        assert!(res.log.contains(&log("source_chain", "bsc")));
+
+        // replays of the CID or of the nonce are rejected
+        assert!(handle(&mut deps, env(), ok).is_err());
+        let same_nonce = mint(&remote("second"), 1, &[&relayer_keys[0], &relayer_keys[1]]);
+        assert!(handle(&mut deps, env(), same_nonce).is_err());
+
+        let query_msg = QueryMsg::GetProvenance {
+            cid: block.cid().to_string(),
+        };
+        match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::GetProvenance { provenance } => {
+                let provenance = provenance.unwrap();
+                assert_eq!(provenance.source_chain, "bsc");
+                assert_eq!(provenance.tx_hash, "0xabc");
+                assert_eq!(provenance.relayers.len(), 2);
+            }
+            _ => panic!("unexpected answer"),
+        }
+        let query_msg = QueryMsg::GetMetadata {
+            cid: block.cid().to_string(),
+            path: "/".to_string(),
+            format: DocumentFormat::Raw,
+        };
+        match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::GetMetadata { data } => {
+                assert_eq!(data, Document::Raw(Binary::from(block.data())))
+            }
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn snip721_tokens() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let data = MetadataSchema {
+            name: "XDV metadata sample: NFT".to_string(),
+            description: "testing sample".to_string(),
+            image: "https://explore.ipld.io/#/explore/QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D"
+                .to_string(),
+            sources: vec!["QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string()],
+            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            refs: vec![],
+            owner: Some("creator".to_string()),
+            verified_credential: None,
+            licenses: None,
+            schema: None,
+        };
+        let payload = HandleMsg::AddMetadata {
+            data: data.clone(),
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        // writing the document first does not make someone its owner
+        let _ = handle(&mut deps, mock_env("someone", &[]), payload).unwrap();
+        let metadata_cid = encode_metadata(&data).unwrap().cid().to_string();
+
+        let mint = HandleMsg::MintNft {
+            token_id: "ancon-1".to_string(),
+            metadata_cid: metadata_cid.clone(),
+            owner: None,
+            signature: None,
+        };
+        // only the owner of the metadata can mint it, and only once
+        assert!(handle(&mut deps, mock_env("someone", &[]), mint.clone()).is_err());
+        let _ = handle(&mut deps, mock_env("creator", &[]), mint).unwrap();
+        let again = HandleMsg::MintNft {
+            token_id: "ancon-2".to_string(),
+            metadata_cid: metadata_cid.clone(),
+            owner: None,
+            signature: None,
+        };
+        assert!(handle(&mut deps, mock_env("creator", &[]), again).is_err());
+
+        let info = QueryMsg::NftInfo {
+            token_id: "ancon-1".to_string(),
+        };
+        match from_binary(&query(&deps, info).unwrap()).unwrap() {
+            QueryAnswer::NftInfo {
+                token_uri,
+                extension,
+            } => {
+                assert_eq!(token_uri, None);
+                let extension = extension.unwrap();
+                assert_eq!(extension.name, Some(data.name.clone()));
+                assert_eq!(extension.attributes, None);
+            }
+            _ => panic!("unexpected answer"),
+        }
+
+        for (addr, key) in &[("creator", "creator key"), ("bob", "bob key")] {
+            let set_key = HandleMsg::SetViewingKey {
+                key: key.to_string(),
+            };
+            let _ = handle(&mut deps, mock_env(*addr, &[]), set_key).unwrap();
+        }
+        let now = BlockTime::from(&mock_env("creator", &[]).block);
+        let private = |addr: &str, key: &str| QueryMsg::PrivateMetadata {
+            token_id: "ancon-1".to_string(),
+            viewer: ViewerInfo {
+                address: HumanAddr::from(addr),
+                viewing_key: key.to_string(),
+            },
+            block: Some(now),
+        };
+        assert!(query(&deps, private("creator", "wrong key")).is_err());
+        assert!(query(&deps, private("bob", "bob key")).is_err());
+        match from_binary(&query(&deps, private("creator", "creator key")).unwrap()).unwrap() {
+            QueryAnswer::PrivateMetadata {
+                token_uri,
+                extension,
+            } => {
+                assert_eq!(token_uri, Some(format!("ipfs://{}", metadata_cid)));
+                let attributes = extension.unwrap().attributes.unwrap();
+                assert_eq!(attributes.len(), 3);
+                assert_eq!(attributes[1].trait_type, Some("source".to_string()));
+                assert_eq!(attributes[2].value, "creator");
+            }
+            _ => panic!("unexpected answer"),
+        }
+
+        // an approved spender can read private metadata and transfer the token
+        let transfer = |to: &str| HandleMsg::TransferNft {
+            recipient: HumanAddr::from(to),
+            token_id: "ancon-1".to_string(),
+        };
+        assert!(handle(&mut deps, mock_env("bob", &[]), transfer("bob")).is_err());
+        let approve = HandleMsg::Approve {
+            spender: HumanAddr::from("bob"),
+            token_id: "ancon-1".to_string(),
+            expires: None,
+        };
+        let _ = handle(&mut deps, mock_env("creator", &[]), approve).unwrap();
+        assert!(query(&deps, private("bob", "bob key")).is_ok());
+
+        let owner_of = QueryMsg::OwnerOf {
+            token_id: "ancon-1".to_string(),
+            viewer: Some(ViewerInfo {
+                address: HumanAddr::from("creator"),
+                viewing_key: "creator key".to_string(),
+            }),
+            include_expired: None,
+            block: Some(now),
+        };
+        match from_binary(&query(&deps, owner_of).unwrap()).unwrap() {
+            QueryAnswer::OwnerOf { owner, approvals } => {
+                assert_eq!(owner, HumanAddr::from("creator"));
+                assert_eq!(approvals.len(), 1);
+            }
+            _ => panic!("unexpected answer"),
+        }
+
+        let _ = handle(&mut deps, mock_env("bob", &[]), transfer("bob")).unwrap();
+        // approvals do not survive a transfer
+        assert!(handle(&mut deps, mock_env("creator", &[]), transfer("creator")).is_err());
+
+        // operators act for all tokens of an owner until their approval expires
+        let approve_all = HandleMsg::ApproveAll {
+            operator: HumanAddr::from("carol"),
+            expires: Some(Expiration::AtHeight(mock_env("bob", &[]).block.height + 1)),
+        };
+        let _ = handle(&mut deps, mock_env("bob", &[]), approve_all).unwrap();
+        let mut later = mock_env("carol", &[]);
+        later.block.height += 1;
+        assert!(handle(&mut deps, later, transfer("carol")).is_err());
+        let _ = handle(&mut deps, mock_env("carol", &[]), transfer("carol")).unwrap();
+
+        let owner_of = QueryMsg::OwnerOf {
+            token_id: "ancon-1".to_string(),
+            viewer: None,
+            include_expired: None,
+            block: None,
+        };
+        match from_binary(&query(&deps, owner_of).unwrap()).unwrap() {
+            QueryAnswer::OwnerOf { owner, approvals } => {
+                assert_eq!(owner, HumanAddr::from("carol"));
+                assert!(approvals.is_empty());
+            }
+            _ => panic!("unexpected answer"),
+        }
+
+        // documents without an owner are minted with a signature by one of their signers
+        let mut signed = data.clone();
+        signed.name = "Signed".to_string();
+        signed.owner = None;
+        let block = encode_metadata(&signed).unwrap();
+        let signing_key = ed25519_zebra::SigningKey::from([1u8; 32]);
+        let pubkey: [u8; 32] = ed25519_zebra::VerificationKey::from(&signing_key).into();
+        let sig: [u8; 64] = signing_key.sign(block.data()).into();
+        let payload = HandleMsg::AddMetadata {
+            data: signed,
+            path: "/".to_string(),
+            signature: Some(Signature {
+                pubkey: Binary::from(&pubkey[..]),
+                algo: SignatureAlgo::Ed25519,
+                sig: Binary::from(&sig[..]),
+            }),
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        let _ = handle(&mut deps, mock_env("creator", &[]), payload).unwrap();
+        let mint = |signature: Option<Signature>| HandleMsg::MintNft {
+            token_id: "ancon-3".to_string(),
+            metadata_cid: block.cid().to_string(),
+            owner: None,
+            signature,
+        };
+        assert!(handle(&mut deps, mock_env("creator", &[]), mint(None)).is_err());
+        let mint_sig: [u8; 64] = signing_key
+            .sign(&nft_mint_payload(block.cid(), "ancon-3").unwrap())
+            .into();
+        let other_key = ed25519_zebra::SigningKey::from([2u8; 32]);
+        let other_pubkey: [u8; 32] = ed25519_zebra::VerificationKey::from(&other_key).into();
+        let other_sig: [u8; 64] = other_key
+            .sign(&nft_mint_payload(block.cid(), "ancon-3").unwrap())
+            .into();
+        let stranger = Signature {
+            pubkey: Binary::from(&other_pubkey[..]),
+            algo: SignatureAlgo::Ed25519,
+            sig: Binary::from(&other_sig[..]),
+        };
+        assert!(handle(&mut deps, mock_env("anyone", &[]), mint(Some(stranger))).is_err());
+        let signer = Signature {
+            pubkey: Binary::from(&pubkey[..]),
+            algo: SignatureAlgo::Ed25519,
+            sig: Binary::from(&mint_sig[..]),
+        };
+        let _ = handle(&mut deps, mock_env("anyone", &[]), mint(Some(signer))).unwrap();
+    }
+
+    #[test]
+    fn license_terms_over_lineage() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let photo = "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string();
+        let original = MetadataSchema {
+            name: "Original".to_string(),
+            description: "testing sample".to_string(),
+            image: "".to_string(),
+            sources: vec![photo.clone()],
+            parent: photo.clone(),
+            refs: vec![],
+            owner: None,
+            verified_credential: None,
+            licenses: Some(vec![SourceLicense {
+                source: photo.clone(),
+                license: "CC-BY-4.0".to_string(),
+                royalty_bps: 500,
+                payee: "alice".to_string(),
+            }]),
+            schema: None,
+        };
+        let original_cid = encode_metadata(&original).unwrap().cid().to_string();
+
+        let mut derivative = original.clone();
+        derivative.name = "Derivative".to_string();
+        derivative.parent = original_cid.clone();
+        derivative.sources = vec![original_cid.clone(), photo.clone()];
+        derivative.licenses = Some(vec![
+            SourceLicense {
+                source: original_cid.clone(),
+                license: "MIT".to_string(),
+                royalty_bps: 250,
+                payee: "bob".to_string(),
+            },
+            SourceLicense {
+                source: photo.clone(),
+                license: photo.clone(),
+                royalty_bps: 100,
+                payee: "alice".to_string(),
+            },
+        ]);
+
+        // licenses must point at a declared source and stay within 100%
+        let mut invalid = original.clone();
+        invalid.licenses.as_mut().unwrap()[0].royalty_bps = 10_001;
+        assert!(encode_metadata(&invalid).is_err());
+        invalid.licenses.as_mut().unwrap()[0].royalty_bps = 10;
+        invalid.licenses.as_mut().unwrap()[0].source = original_cid.clone();
+        assert!(encode_metadata(&invalid).is_err());
+        // payees are addresses or DIDs
+        let mut invalid = original.clone();
+        invalid.licenses.as_mut().unwrap()[0].payee = "x".to_string();
+        let payload = HandleMsg::AddMetadata {
+            data: invalid,
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        assert!(handle(&mut deps, mock_env("creator", &[]), payload).is_err());
+
+        for data in vec![original, derivative.clone()] {
+            let payload = HandleMsg::AddMetadata {
+                data,
+                path: "/".to_string(),
+                signature: None,
+                issuer_key: None,
+                private: false,
+                link_policy: None,
+            };
+            let _ = handle(&mut deps, mock_env("creator", &[]), payload).unwrap();
+        }
+
+        let cid = encode_metadata(&derivative).unwrap().cid().to_string();
+        let resp = query(&deps, QueryMsg::LicenseTerms { cid: cid.clone() }).unwrap();
+        match from_binary(&resp).unwrap() {
+            QueryAnswer::LicenseTerms { terms, royalties } => {
+                assert_eq!(terms.len(), 3);
+                assert_eq!(terms[0].declared_in, cid);
+                assert_eq!(terms[0].license, "MIT");
+                assert_eq!(terms[1].license, photo);
+                assert_eq!(terms[2].declared_in, original_cid);
+                assert_eq!(terms[2].license, "CC-BY-4.0");
+                assert_eq!(royalties.len(), 2);
+                assert_eq!(royalties[0].payee, "bob");
+                assert_eq!(royalties[0].royalty_bps, 250);
+                assert_eq!(royalties[1].payee, "alice");
+                assert_eq!(royalties[1].royalty_bps, 600);
+            }
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn write_fees() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let fees = vec![FeeRate {
+            denom: "uscrt".to_string(),
+            flat: Uint128(1000),
+            per_byte: Uint128(10),
+        }];
+        let set_fees = HandleMsg::SetFees { fees: fees.clone() };
+        assert!(handle(&mut deps, mock_env("someone", &[]), set_fees.clone()).is_err());
+        let _ = handle(&mut deps, mock_env("creator", &[]), set_fees).unwrap();
+
+        let data = MetadataSchema {
+            name: "Paid".to_string(),
+            description: "testing sample".to_string(),
+            image: "".to_string(),
+            sources: vec![],
+            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            refs: vec![],
+            owner: None,
+            verified_credential: None,
+            licenses: None,
+            schema: None,
+        };
+        let bytes = encode_metadata(&data).unwrap().data().len() as u64;
+        let resp = query(&deps, QueryMsg::EstimateFee { bytes, ops: None }).unwrap();
+        let fee = match from_binary(&resp).unwrap() {
+            QueryAnswer::EstimateFee { fees } => fees[0].amount.u128(),
+            _ => panic!("unexpected answer"),
+        };
+        assert_eq!(fee, 1000 + 10 * bytes as u128);
+
+        let payload = HandleMsg::AddMetadata {
+            data,
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        let short = coins(fee - 1, "uscrt");
+        assert!(handle(&mut deps, mock_env("writer", &short), payload.clone()).is_err());
+        let other_denom = coins(fee, "uatom");
+        assert!(handle(&mut deps, mock_env("writer", &other_denom), payload.clone()).is_err());
+        // only the fee is kept, whatever else was sent goes back to the writer
+        let overpaid = vec![coin(fee + 5, "uscrt"), coin(7, "uatom")];
+        let res = handle(&mut deps, mock_env("writer", &overpaid), payload).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                to_address: HumanAddr::from("writer"),
+                amount: vec![coin(5, "uscrt"), coin(7, "uatom")],
+            })]
+        );
+
+        // messages that are not writes take no funds
+        let register = HandleMsg::RegisterDid {
+            did: "did:xdv:writer".to_string(),
+            controllers: None,
+        };
+        let paid = mock_env("writer", &coins(fee, "uscrt"));
+        let err = handle(&mut deps, paid, register).unwrap_err();
+        assert_eq!(err, StdError::generic_err("This message does not accept funds"));
+
+        let withdraw = HandleMsg::WithdrawFees {
+            recipient: HumanAddr::from("pinning"),
+            amount: Some(coins(fee + 1, "uscrt")),
+        };
+        assert!(handle(&mut deps, mock_env("creator", &[]), withdraw).is_err());
+        let withdraw = HandleMsg::WithdrawFees {
+            recipient: HumanAddr::from("pinning"),
+            amount: None,
+        };
+        assert!(handle(&mut deps, mock_env("someone", &[]), withdraw.clone()).is_err());
+        let res = handle(&mut deps, mock_env("creator", &[]), withdraw.clone()).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                to_address: HumanAddr::from("pinning"),
+                amount: coins(fee, "uscrt"),
+            })]
+        );
+        // nothing is left to withdraw
+        assert!(handle(&mut deps, mock_env("creator", &[]), withdraw).is_err());
+    }
+
+    #[test]
+    fn write_quotas() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let data = |name: &str| MetadataSchema {
+            name: name.to_string(),
+            description: "testing sample".to_string(),
+            image: "".to_string(),
+            sources: vec![],
+            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            refs: vec![],
+            owner: None,
+            verified_credential: None,
+            licenses: None,
+            schema: None,
+        };
+        let add = |name: &str| HandleMsg::AddMetadata {
+            data: data(name),
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        let size = encode_metadata(&data("one")).unwrap().data().len() as u64;
+
+        let set_quotas = HandleMsg::SetQuotas {
+            max_bytes: Some(size * 2),
+            max_writes_per_block: Some(1),
+        };
+        assert!(handle(&mut deps, mock_env("writer", &[]), set_quotas.clone()).is_err());
+        let _ = handle(&mut deps, mock_env("creator", &[]), set_quotas).unwrap();
+
+        let _ = handle(&mut deps, mock_env("writer", &[]), add("one")).unwrap();
+        // a second write in the same block is rate limited
+        let err = handle(&mut deps, mock_env("writer", &[]), add("two")).unwrap_err();
+        assert_eq!(err, StdError::generic_err(r#"{"rate_limited":{"limit":1}}"#));
+        // other addresses have their own limits
+        let _ = handle(&mut deps, mock_env("other", &[]), add("two")).unwrap();
+
+        let mut next = mock_env("writer", &[]);
+        next.block.height += 1;
+        let _ = handle(&mut deps, next.clone(), add("two")).unwrap();
+        next.block.height += 1;
+        let err = handle(&mut deps, next, add("six")).unwrap_err();
+        let quota_err = match err {
+            StdError::GenericErr { msg, .. } => from_slice::<QuotaError>(msg.as_bytes()).unwrap(),
+            err => panic!("unexpected error {}", err),
+        };
+        assert_eq!(
+            quota_err,
+            QuotaError::QuotaExceeded {
+                used: size * 2,
+                requested: size,
+                quota: size * 2
+            }
+        );
+
+        let resp = query(
+            &deps,
+            QueryMsg::Usage {
+                address: HumanAddr::from("writer"),
+            },
+        )
+        .unwrap();
+        match from_binary(&resp).unwrap() {
+            QueryAnswer::Usage {
+                bytes_used,
+                block_count,
+                remaining_bytes,
+            } => {
+                assert_eq!(bytes_used, size * 2);
+                assert_eq!(block_count, 2);
+                assert_eq!(remaining_bytes, Some(0));
+            }
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn validation_rules() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
 
-        // we can just call .unwrap() to assert this was a success
-        let _ = init(&mut deps, env, msg).unwrap();
-        
-        //Adding Metadata for the test
-        let data_payload = MetadataSchema {
-            name: "XDV metadata sample: NFT".to_string(),
+        let file = |path: &str, content_type: &str, content: Vec<u8>| HandleMsg::AddFile {
+            path: path.to_string(),
+            content_type: content_type.to_string(),
+            time: 1,
+            content,
+            mode: "0644".to_string(),
+            private: false,
+            encoding: FileEncoding::DagCbor,
+        };
+
+        // the path grammar applies even without configured rules
+        let err = handle(&mut deps, mock_env("writer", &[]), file("/a::b", "text/plain", vec![1]))
+            .unwrap_err();
+        assert_eq!(err, StdError::generic_err("Path /a::b cannot contain ::"));
+        // relative paths are only rejected once the rules require absolute ones
+        let _ = handle(&mut deps, mock_env("writer", &[]), file("a", "text/plain", vec![1])).unwrap();
+        assert!(
+            handle(&mut deps, mock_env("writer", &[]), file("/../a", "text/plain", vec![1])).is_err()
+        );
+
+        let set_rules = HandleMsg::SetValidationRules {
+            rules: ValidationRules {
+                max_content_size: Some(4),
+                max_path_length: Some(8),
+                allowed_content_types: vec!["text/*".to_string()],
+                link_policy: LinkPolicy::Any,
+                require_absolute_paths: true,
+            },
+        };
+        assert!(handle(&mut deps, mock_env("writer", &[]), set_rules.clone()).is_err());
+        let _ = handle(&mut deps, mock_env("creator", &[]), set_rules).unwrap();
+
+        let _ = handle(&mut deps, mock_env("writer", &[]), file("/a.txt", "text/plain", vec![1; 4]))
+            .unwrap();
+        let err = handle(&mut deps, mock_env("writer", &[]), file("/a.txt", "text/plain", vec![1; 5]))
+            .unwrap_err();
+        assert_eq!(err, StdError::generic_err("Content is 5 bytes, the maximum is 4"));
+        let err = handle(&mut deps, mock_env("writer", &[]), file("/a.png", "image/png", vec![1]))
+            .unwrap_err();
+        assert_eq!(err, StdError::generic_err("Content type image/png is not allowed"));
+        assert!(
+            handle(&mut deps, mock_env("writer", &[]), file("/long/name.txt", "text/plain", vec![1]))
+                .is_err()
+        );
+        let relative = file("b.txt", "text/plain", vec![1]);
+        assert!(handle(&mut deps, mock_env("writer", &[]), relative).is_err());
+
+        let resp = query(&deps, QueryMsg::ValidationRules {}).unwrap();
+        match from_binary(&resp).unwrap() {
+            QueryAnswer::ValidationRules { rules } => assert_eq!(rules.max_content_size, Some(4)),
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn migrate_legacy_keys() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        // fixture: blocks written by the previous version under "<cid>::<path>" keys
+        let metadata = encode_metadata(&MetadataSchema {
+            name: "legacy".to_string(),
             description: "testing sample".to_string(),
-            image:
-                "https://explore.ipld.io/#/explore/QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D"
-                    .to_string(),
-            sources: vec!["QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string()],
+            image: "".to_string(),
+            sources: vec![],
             parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
-            refs: vec![
-                "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
-                "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
-            ],
+            refs: vec![],
+            owner: None,
+            verified_credential: None,
+            licenses: None,
+            schema: None,
+        })
+        .unwrap();
+        let file = Block::<DefaultParams>::encode(
+            DagCborCodec,
+            Code::Sha2_256,
+            &ipld!({
+                "owner": "legacy",
+                "path": "/doc.txt",
+                "type": "text/plain",
+                "content": vec![1u8, 2, 3],
+                "time": 1,
+                "mode": "0644"
+            }),
+        )
+        .unwrap();
+        let legacy = |block: &IpldBlock, path: &str| {
+            (format!("{}::{}", block.cid(), path), block.data().to_vec())
         };
-        let cid = "bafyreicnuvbp2lhmanra7r5o564fo4n5hhynqmwqv5l3ymz27gqbmlf2xa".to_string();
-        // add metadata
+        for (key, data) in vec![legacy(&metadata, "/"), legacy(&file, "/doc.txt")] {
+            crate::state::save_to_store(&mut deps.storage, key.as_bytes(), &data).unwrap();
+        }
+        let keys = vec![
+            LegacyKey {
+                cid: metadata.cid().to_string(),
+                path: "/".to_string(),
+                kind: LegacyKind::Metadata,
+                owner: None,
+            },
+            LegacyKey {
+                cid: file.cid().to_string(),
+                path: "/doc.txt".to_string(),
+                kind: LegacyKind::File,
+                owner: None,
+            },
+        ];
 
-        // add metadata - success message
-        let payload_m = HandleMsg::AddMetadata {
-            data: data_payload,
+        let get_file = QueryMsg::GetFile {
+            cid: file.cid().to_string(),
+            path: "/doc.txt".to_string(),
+            format: DocumentFormat::Raw,
+        };
+        assert!(query(&deps, get_file.clone()).is_err());
+
+        let migrate = HandleMsg::MigrateStorage { keys };
+        assert!(handle(&mut deps, mock_env("writer", &[]), migrate.clone()).is_err());
+        let resp = handle(&mut deps, mock_env("creator", &[]), migrate.clone()).unwrap();
+        match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::MigrateStorage { migrated } => assert_eq!(migrated, 2),
+            _ => panic!("unexpected answer"),
+        }
+        let key = format!("{}::/", metadata.cid());
+        assert_eq!(deps.storage.get(key.as_bytes()), None);
+
+        match from_binary(&query(&deps, get_file).unwrap()).unwrap() {
+            QueryAnswer::GetFile { data } => {
+                assert_eq!(data, Document::Raw(Binary::from(file.data())))
+            }
+            _ => panic!("unexpected answer"),
+        }
+        let get_metadata = QueryMsg::GetMetadata {
+            cid: metadata.cid().to_string(),
             path: "/".to_string(),
+            format: DocumentFormat::Raw,
+        };
+        match from_binary(&query(&deps, get_metadata).unwrap()).unwrap() {
+            QueryAnswer::GetMetadata { data } => {
+                assert_eq!(data, Document::Raw(Binary::from(metadata.data())))
+            }
+            _ => panic!("unexpected answer"),
+        }
+        // the file is not readable as metadata
+        let as_metadata = QueryMsg::GetMetadata {
+            cid: file.cid().to_string(),
+            path: "/doc.txt".to_string(),
+            format: DocumentFormat::Raw,
         };
+        assert!(query(&deps, as_metadata).is_err());
 
-        let resp: HandleResponse =
-            handle(&mut deps, mock_env("creator", &collateral), payload_m).unwrap();
+        // running it again finds nothing left to move
+        let resp = handle(&mut deps, mock_env("creator", &[]), migrate).unwrap();
+        match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::MigrateStorage { migrated } => assert_eq!(migrated, 0),
+            _ => panic!("unexpected answer"),
+        }
+    }
 
-        // get metadata
-        
-        // get metadata - success message
-        let payload_q = QueryMsg::GetMetadata {
-            cid: cid,
+    #[test]
+    fn migrate_from_unversioned() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+
+        // fixture: storage as written by 0.1.0, with no version record and a three field State
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct OldState {
+            owner: CanonicalAddr,
+            tag: String,
+            paused: bool,
+        }
+        let creator = deps.api.canonical_address(&HumanAddr::from("creator")).unwrap();
+        let old_state = OldState {
+            owner: creator,
+            tag: "test".to_string(),
+            paused: false,
+        };
+        cosmwasm_storage::singleton(&mut deps.storage, crate::state::CONFIG_KEY)
+            .save(&old_state)
+            .unwrap();
+        let file = Block::<DefaultParams>::encode(
+            DagCborCodec,
+            Code::Sha2_256,
+            &ipld!({
+                "owner": "legacy",
+                "path": "/doc.txt",
+                "type": "text/plain",
+                "content": vec![1u8, 2, 3],
+                "time": 1,
+                "mode": "0644"
+            }),
+        )
+        .unwrap();
+        let key = format!("{}::/doc.txt", file.cid());
+        crate::state::save_to_store(&mut deps.storage, key.as_bytes(), &file.data().to_vec())
+            .unwrap();
+
+        let msg = MigrateMsg {
+            legacy_keys: vec![LegacyKey {
+                cid: file.cid().to_string(),
+                path: "/doc.txt".to_string(),
+                kind: LegacyKind::File,
+                owner: Some(HumanAddr::from("writer")),
+            }],
+        };
+        let resp = migrate(&mut deps, mock_env("admin", &[]), msg.clone()).unwrap();
+        assert_eq!(resp.log[1], log("from_version", UNVERSIONED));
+
+        match from_binary(&query(&deps, QueryMsg::ContractVersion {}).unwrap()).unwrap() {
+            QueryAnswer::ContractVersion { name, version } => {
+                assert_eq!(name, CONTRACT_NAME);
+                assert_eq!(version, CONTRACT_VERSION);
+            }
+            _ => panic!("unexpected answer"),
+        }
+        // UpgradeState wrote the new State fields
+        let config_key = cosmwasm_storage::to_length_prefixed(crate::state::CONFIG_KEY);
+        let raw = deps.storage.get(&config_key).unwrap();
+        assert!(String::from_utf8(raw).unwrap().contains("\"validation\""));
+        // RekeyStorage moved the block and BackfillIndexes credited its writer
+        assert_eq!(deps.storage.get(key.as_bytes()), None);
+        let get_file = QueryMsg::GetFile {
+            cid: file.cid().to_string(),
+            path: "/doc.txt".to_string(),
+            format: DocumentFormat::Raw,
+        };
+        query(&deps, get_file).unwrap();
+        let index = may_load_block_index(&deps.storage, file.cid()).unwrap().unwrap();
+        assert_eq!(
+            index.owner,
+            deps.api.canonical_address(&HumanAddr::from("writer")).unwrap()
+        );
+
+        // nothing is pending at the current version
+        let resp = migrate(&mut deps, mock_env("admin", &[]), msg.clone()).unwrap();
+        assert_eq!(resp.log[1], log("from_version", CONTRACT_VERSION));
+
+        contract_version(&mut deps.storage)
+            .save(&ContractVersion {
+                name: "other_contract".to_string(),
+                version: "0.1.0".to_string(),
+            })
+            .unwrap();
+        assert!(migrate(&mut deps, mock_env("admin", &[]), msg).is_err());
+    }
+
+    #[test]
+    fn migrate_storage_upgrades() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+        // fixture: a store left at 0.0.0 because the chain never called migrate
+        contract_version(&mut deps.storage)
+            .save(&ContractVersion {
+                name: CONTRACT_NAME.to_string(),
+                version: UNVERSIONED.to_string(),
+            })
+            .unwrap();
+        let file = Block::<DefaultParams>::encode(
+            DagCborCodec,
+            Code::Sha2_256,
+            &ipld!({
+                "owner": "legacy",
+                "path": "/doc.txt",
+                "type": "text/plain",
+                "content": vec![1u8, 2, 3],
+                "time": 1,
+                "mode": "0644"
+            }),
+        )
+        .unwrap();
+        let key = format!("{}::/doc.txt", file.cid());
+        crate::state::save_to_store(&mut deps.storage, key.as_bytes(), &file.data().to_vec())
+            .unwrap();
+
+        let migrate = HandleMsg::MigrateStorage {
+            keys: vec![LegacyKey {
+                cid: file.cid().to_string(),
+                path: "/doc.txt".to_string(),
+                kind: LegacyKind::File,
+                owner: Some(HumanAddr::from("writer")),
+            }],
+        };
+        assert!(handle(&mut deps, mock_env("writer", &[]), migrate.clone()).is_err());
+        let resp = handle(&mut deps, mock_env("creator", &[]), migrate).unwrap();
+        assert_eq!(resp.log[2], log("from_version", UNVERSIONED));
+        match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::MigrateStorage { migrated } => assert_eq!(migrated, 1),
+            _ => panic!("unexpected answer"),
+        }
+        assert_eq!(
+            contract_version_read(&deps.storage).load().unwrap().version,
+            CONTRACT_VERSION
+        );
+        let index = may_load_block_index(&deps.storage, file.cid()).unwrap().unwrap();
+        assert_eq!(
+            index.owner,
+            deps.api.canonical_address(&HumanAddr::from("writer")).unwrap()
+        );
+    }
+
+    #[test]
+    fn write_logs() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let add = |name: &str, private: bool| HandleMsg::AddMetadata {
+            data: MetadataSchema {
+                name: name.to_string(),
+                description: "testing sample".to_string(),
+                image: "".to_string(),
+                sources: vec![],
+                parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+                refs: vec![],
+                owner: None,
+                verified_credential: None,
+                licenses: None,
+                schema: None,
+            },
+            path: "/docs".to_string(),
+            signature: None,
+            issuer_key: None,
+            private,
+            link_policy: None,
+        };
+
+        let resp = handle(&mut deps, mock_env("writer", &[]), add("public", false)).unwrap();
+        let keys: Vec<&str> = resp.log.iter().map(|l| l.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec!["action", "cid", "codec", "size", "path", "owner", "private", "parent"]
+        );
+        assert_eq!(resp.log[0], log("action", "add_metadata"));
+        assert_eq!(resp.log[2], log("codec", "dag-cbor"));
+        assert_eq!(resp.log[4], log("path", "/docs"));
+        assert_eq!(resp.log[5], log("owner", "writer"));
+        assert_eq!(
+            resp.log[7],
+            log("parent", "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D")
+        );
+
+        let resp = handle(&mut deps, mock_env("writer", &[]), add("private", true)).unwrap();
+        for key in &["path", "owner", "parent"] {
+            let attr = resp.log.iter().find(|l| l.key == *key).unwrap();
+            assert_eq!(attr.value, crate::events::REDACTED);
+        }
+        assert_eq!(resp.log[6], log("private", true));
+
+        let resp = handle(
+            &mut deps,
+            mock_env("writer", &[]),
+            HandleMsg::SetViewingKey {
+                key: "secret".to_string(),
+            },
+        )
+        .unwrap();
+        assert!(resp.log.iter().all(|l| !l.value.contains("secret")));
+    }
+
+    #[test]
+    fn batch_writes() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let set_fees = HandleMsg::SetFees {
+            fees: vec![FeeRate {
+                denom: "uscrt".to_string(),
+                flat: Uint128(1000),
+                per_byte: Uint128(0),
+            }],
+        };
+        let _ = handle(&mut deps, mock_env("creator", &[]), set_fees).unwrap();
+
+        let file = Op::AddFile {
+            path: "/doc.txt".to_string(),
+            content: vec![1, 2, 3],
+            mode: "0644".to_string(),
+            time: 1,
+            content_type: "text/plain".to_string(),
+            private: false,
+            encoding: FileEncoding::DagCbor,
+        };
+        let metadata = MetadataSchema {
+            name: "Batched".to_string(),
+            description: "testing sample".to_string(),
+            image: "".to_string(),
+            sources: vec![],
+            parent: "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string(),
+            refs: vec![],
+            owner: None,
+            verified_credential: None,
+            licenses: None,
+            schema: None,
+        };
+        let raw = Block::<DefaultParams>::encode(DagCborCodec, Code::Sha2_256, &ipld!({"a": 1}))
+            .unwrap();
+        let ops = vec![
+            file.clone(),
+            Op::AddMetadata {
+                data: metadata.clone(),
+                path: "/".to_string(),
+                signature: None,
+                issuer_key: None,
+                private: false,
+                link_policy: None,
+            },
+            Op::PutBlock {
+                cid: raw.cid().to_string(),
+                data: Binary::from(raw.data()),
+            },
+        ];
+
+        // each of the three writes pays the flat fee
+        let env = mock_env("writer", &coins(2999, "uscrt"));
+        assert!(handle(&mut deps, env, HandleMsg::Batch { ops: ops.clone() }).is_err());
+        let env = mock_env("writer", &coins(3000, "uscrt"));
+        let resp = handle(&mut deps, env, HandleMsg::Batch { ops }).unwrap();
+        let cids = match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::Batch { cids } => cids,
+            _ => panic!("unexpected answer"),
+        };
+        assert_eq!(cids.len(), 3);
+        assert_eq!(cids[1], encode_metadata(&metadata).unwrap().cid().to_string());
+        assert_eq!(cids[2], raw.cid().to_string());
+        let actions: Vec<&str> = resp
+            .log
+            .iter()
+            .filter(|l| l.key == "action")
+            .map(|l| l.value.as_str())
+            .collect();
+        assert_eq!(actions, vec!["batch", "add_file", "add_metadata", "put_block"]);
+
+        let get_file = QueryMsg::GetFile {
+            cid: cids[0].clone(),
+            path: "/doc.txt".to_string(),
+            format: DocumentFormat::Raw,
+        };
+        query(&deps, get_file).unwrap();
+
+        // a failing operation fails the whole message
+        let bad = Op::PutBlock {
+            cid: raw.cid().to_string(),
+            data: Binary::from(b"not the block".to_vec()),
+        };
+        let env = mock_env("writer", &coins(1000, "uscrt"));
+        let err = handle(&mut deps, env, HandleMsg::Batch { ops: vec![file.clone(), bad] }).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!(
+                "Operation 1 failed: Block data does not hash to {}",
+                raw.cid()
+            ))
+        );
+        // the error keeps its kind
+        let unparsable = Op::PutBlock {
+            cid: "not a cid".to_string(),
+            data: Binary::from(raw.data()),
+        };
+        let env = mock_env("writer", &coins(1000, "uscrt"));
+        let err = handle(&mut deps, env, HandleMsg::Batch { ops: vec![file.clone(), unparsable] })
+            .unwrap_err();
+        match err {
+            StdError::ParseErr { msg, .. } => assert!(msg.starts_with("Operation 1 failed: ")),
+            err => panic!("unexpected error {}", err),
+        }
+        assert!(handle(&mut deps, mock_env("writer", &[]), HandleMsg::Batch { ops: vec![] }).is_err());
+
+        // metadata can link to the file written before it
+        let linked = MetadataSchema {
+            refs: vec!["$op:0".to_string()],
+            parent: "$op:0".to_string(),
+            ..metadata.clone()
+        };
+        let add_linked = |data: MetadataSchema| Op::AddMetadata {
+            data,
             path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
         };
-        let resp: Binary =
-            query(&mut deps, payload_q).unwrap();
+        let ops = vec![file.clone(), add_linked(linked.clone())];
+        let env = mock_env("writer", &coins(2000, "uscrt"));
+        let resp = handle(&mut deps, env, HandleMsg::Batch { ops }).unwrap();
+        let cids = match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::Batch { cids } => cids,
+            _ => panic!("unexpected answer"),
+        };
+        let resolved = MetadataSchema {
+            refs: vec![cids[0].clone()],
+            parent: cids[0].clone(),
+            ..metadata
+        };
+        assert_eq!(cids[1], encode_metadata(&resolved).unwrap().cid().to_string());
 
-        let object = from_binary(&resp).unwrap();
-        match object {
-            QueryAnswer::GetFile { data } => {}
+        // only earlier operations can be named
+        let ops = vec![add_linked(linked.clone()), file.clone()];
+        let env = mock_env("writer", &coins(1000, "uscrt"));
+        let err = handle(&mut deps, env, HandleMsg::Batch { ops }).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Operation 0 failed: $op:0 does not name an earlier operation")
+        );
+        let add = HandleMsg::AddMetadata {
+            data: linked,
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        assert!(handle(&mut deps, mock_env("writer", &coins(1000, "uscrt")), add).is_err());
+
+        // every op counts against the per block write limit
+        let set_quotas = HandleMsg::SetQuotas {
+            max_bytes: None,
+            max_writes_per_block: Some(1),
+        };
+        let _ = handle(&mut deps, mock_env("creator", &[]), set_quotas).unwrap();
+        let two = vec![file.clone(), add_linked(resolved)];
+        let env = mock_env("limited", &coins(2000, "uscrt"));
+        let err = handle(&mut deps, env, HandleMsg::Batch { ops: two }).unwrap_err();
+        assert_eq!(err, StdError::generic_err(r#"{"rate_limited":{"limit":1}}"#));
+        let env = mock_env("limited", &coins(1000, "uscrt"));
+        let _ = handle(&mut deps, env, HandleMsg::Batch { ops: vec![file] }).unwrap();
+    }
+
+    #[test]
+    fn import_car() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let hello = Ipld::Bytes(b"hello".to_vec());
+        let leaf = Block::<DefaultParams>::encode(RawCodec, Code::Sha2_256, &hello).unwrap();
+        let root = Block::<DefaultParams>::encode(
+            DagCborCodec,
+            Code::Sha2_256,
+            &ipld!({"leaf": Ipld::Link(*leaf.cid())}),
+        )
+        .unwrap();
+        let blocks = vec![
+            (*root.cid(), root.data().to_vec()),
+            (*leaf.cid(), leaf.data().to_vec()),
+            (*leaf.cid(), leaf.data().to_vec()),
+        ];
+        let car = Binary::from(encode_car(&[*root.cid()], &blocks));
+
+        let resp = handle(&mut deps, mock_env("writer", &[]), HandleMsg::ImportCar { car }).unwrap();
+        match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::ImportCar { roots } => assert_eq!(roots, vec![root.cid().to_string()]),
+            _ => panic!("unexpected answer"),
+        }
+        assert_eq!(resp.log[2], log("blocks", 2));
+        for block in &[&root, &leaf] {
+            assert_eq!(may_load_block_data(&deps.storage, block.cid()).unwrap(), block.data());
+            let index = may_load_block_index(&deps.storage, block.cid()).unwrap().unwrap();
+            assert_eq!(index.owner, deps.api.canonical_address(&HumanAddr::from("writer")).unwrap());
+        }
+
+        // one block that does not hash to its CID rejects the archive
+        let tampered = vec![
+            (*root.cid(), root.data().to_vec()),
+            (*leaf.cid(), b"hullo".to_vec()),
+        ];
+        let car = Binary::from(encode_car(&[*root.cid()], &tampered));
+        let err = handle(&mut deps, mock_env("writer", &[]), HandleMsg::ImportCar { car }).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!("Block data does not hash to {}", leaf.cid()))
+        );
+        let car = Binary::from(b"not a car".to_vec());
+        assert!(handle(&mut deps, mock_env("writer", &[]), HandleMsg::ImportCar { car }).is_err());
+
+        // every root named in the header must be in the archive
+        let car = Binary::from(encode_car(&[*root.cid()], &blocks[1..]));
+        let err = handle(&mut deps, mock_env("writer", &[]), HandleMsg::ImportCar { car }).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!("Root {} is not in the archive", root.cid()))
+        );
+    }
+
+    #[test]
+    fn export_car() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let hello = Ipld::Bytes(b"hello".to_vec());
+        let leaf = Block::<DefaultParams>::encode(RawCodec, Code::Sha2_256, &hello).unwrap();
+        let missing = Cid::from_str("QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D").unwrap();
+        let mid = Block::<DefaultParams>::encode(
+            DagCborCodec,
+            Code::Sha2_256,
+            &ipld!({"leaf": Ipld::Link(*leaf.cid()), "missing": Ipld::Link(missing)}),
+        )
+        .unwrap();
+        let root = Block::<DefaultParams>::encode(
+            DagCborCodec,
+            Code::Sha2_256,
+            &ipld!({"mid": Ipld::Link(*mid.cid()), "leaf": Ipld::Link(*leaf.cid())}),
+        )
+        .unwrap();
+        let blocks: Vec<_> = [&root, &mid, &leaf]
+            .iter()
+            .map(|b| (*b.cid(), b.data().to_vec()))
+            .collect();
+        let car = Binary::from(encode_car(&[*root.cid()], &blocks));
+        let _ = handle(&mut deps, mock_env("writer", &[]), HandleMsg::ImportCar { car }).unwrap();
+
+        let export = |max_depth, max_bytes, cursor| {
+            let msg = QueryMsg::ExportCar {
+                root: root.cid().to_string(),
+                max_depth,
+                max_bytes,
+                cursor,
+            };
+            match from_binary(&query(&deps, msg).unwrap()).unwrap() {
+                QueryAnswer::ExportCar { car, cursor } => (car.as_slice().to_vec(), cursor),
+                _ => panic!("unexpected answer"),
+            }
+        };
+
+        // the whole DAG, each block once and missing links left out
+        let (full, cursor) = export(None, None, None);
+        assert_eq!(cursor, None);
+        let exported = read_car(&full).unwrap();
+        assert_eq!(exported.roots, vec![*root.cid()]);
+        assert_eq!(exported.blocks.len(), 3);
+        assert_eq!(exported.blocks[0], blocks[0]);
+        for block in &blocks {
+            assert!(exported.blocks.contains(block));
+        }
+
+        // one block per page; the pages add up to an archive of the same blocks, where the leaf linked
+        // from both the root and `mid` is repeated once it falls on another page
+        let mut paged = vec![];
+        let mut cursors = vec![];
+        let mut cursor = None;
+        loop {
+            let (page, next) = export(None, Some(1), cursor);
+            paged.extend(page);
+            match next {
+                Some(next) => {
+                    cursors.push(next.clone());
+                    cursor = Some(next)
+                }
+                None => break,
+            }
+        }
+        let paged = read_car(&paged).unwrap();
+        assert_eq!(paged.roots, vec![*root.cid()]);
+        assert_eq!(paged.blocks.len(), 4);
+        for block in &paged.blocks {
+            assert!(blocks.contains(block));
+        }
+        for block in &blocks {
+            assert!(paged.blocks.contains(block));
+        }
+
+        // cursors only walk the DAG under the root they were made for
+        let tampered = encode_cursor(&[(*root.cid(), 2), (*leaf.cid(), 0)]).unwrap();
+        let err = query(
+            &deps,
+            QueryMsg::ExportCar {
+                root: root.cid().to_string(),
+                max_depth: None,
+                max_bytes: None,
+                cursor: Some(Binary::from(tampered)),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!("Export cursor does not belong to {}", root.cid()))
+        );
+        let other_root = QueryMsg::ExportCar {
+            root: mid.cid().to_string(),
+            max_depth: None,
+            max_bytes: None,
+            cursor: Some(cursors[0].clone()),
+        };
+        assert!(query(&deps, other_root).is_err());
+
+        let (shallow, _) = export(Some(0), None, None);
+        assert_eq!(read_car(&shallow).unwrap().blocks, vec![blocks[0].clone()]);
+
+        let msg = QueryMsg::ExportCar {
+            root: missing.to_string(),
+            max_depth: None,
+            max_bytes: None,
+            cursor: None,
+        };
+        assert!(query(&deps, msg).is_err());
+    }
+
+    #[test]
+    fn put_and_get_blocks() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let node = ipld!({"name": "block"});
+        // UnixFS "hello world\n", as added by `ipfs add`
+        let pb_cid = Cid::from_str("QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o").unwrap();
+        let pb_data = hex::decode("0a120802120c68656c6c6f20776f726c640a180c").unwrap();
+        let blocks = vec![
+            Block::<DefaultParams>::encode(RawCodec, Code::Sha2_256, &Ipld::Bytes(b"raw".to_vec()))
+                .unwrap(),
+            Block::<DefaultParams>::encode(DagCborCodec, Code::Sha2_256, &node).unwrap(),
+            Block::<DefaultParams>::encode(DagJsonCodec, Code::Sha2_256, &node).unwrap(),
+            Block::<DefaultParams>::new(pb_cid, pb_data).unwrap(),
+        ];
+        for block in &blocks {
+            let put = HandleMsg::PutBlock {
+                cid: block.cid().to_string(),
+                data: Binary::from(block.data()),
+            };
+            let resp = handle(&mut deps, mock_env("writer", &[]), put).unwrap();
+            assert_eq!(resp.log[2], log("codec", codec_name(block.cid().codec())));
+
+            let get = QueryMsg::GetBlock {
+                cid: block.cid().to_string(),
+            };
+            match from_binary(&query(&deps, get).unwrap()).unwrap() {
+                QueryAnswer::GetBlock { data } => assert_eq!(data.as_slice(), block.data()),
+                _ => panic!("unexpected answer"),
+            }
+        }
+
+        // the bytes must hash to the CID, under a codec we can decode
+        let put = HandleMsg::PutBlock {
+            cid: blocks[1].cid().to_string(),
+            data: Binary::from(blocks[2].data()),
+        };
+        assert_eq!(
+            handle(&mut deps, mock_env("writer", &[]), put).unwrap_err(),
+            StdError::generic_err(format!("Block data does not hash to {}", blocks[1].cid()))
+        );
+        let json = Cid::new_v1(0x0200, *blocks[2].cid().hash());
+        let put = HandleMsg::PutBlock {
+            cid: json.to_string(),
+            data: Binary::from(blocks[2].data()),
+        };
+        assert!(handle(&mut deps, mock_env("writer", &[]), put).is_err());
+
+        let get = QueryMsg::GetBlock { cid: json.to_string() };
+        assert!(query(&deps, get).is_err());
+    }
+
+    #[test]
+    fn unixfs_files() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let file = |content: Vec<u8>| HandleMsg::AddFile {
+            path: "/hello.txt".to_string(),
+            content_type: "text/plain".to_string(),
+            time: 1,
+            content,
+            mode: "0644".to_string(),
+            private: false,
+            encoding: FileEncoding::Unixfs,
+        };
+
+        // same CID as `ipfs add`
+        let resp = handle(&mut deps, mock_env("writer", &[]), file(b"hello world\n".to_vec())).unwrap();
+        match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::AddFile { cid } => {
+                assert_eq!(cid, "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o")
+            }
+            _ => panic!("unexpected answer"),
+        }
+        assert_eq!(resp.log[2], log("codec", "dag-pb"));
+        let get_file = QueryMsg::GetFile {
+            cid: "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o".to_string(),
+            path: "/hello.txt".to_string(),
+            format: DocumentFormat::Raw,
+        };
+        query(&deps, get_file).unwrap();
+
+        // larger files store every chunk, with the root under the path
+        let content = vec![1; crate::unixfs::CHUNK_SIZE + 1];
+        let nodes = encode_file(&content).unwrap();
+        assert_eq!(nodes.len(), 3);
+        let _ = handle(&mut deps, mock_env("writer", &[]), file(content)).unwrap();
+        for node in &nodes {
+            let get = QueryMsg::GetBlock {
+                cid: node.cid.to_string(),
+            };
+            query(&deps, get).unwrap();
+        }
+        let get_file = QueryMsg::GetFile {
+            cid: nodes[2].cid.to_string(),
+            path: "/hello.txt".to_string(),
+            format: DocumentFormat::Raw,
+        };
+        query(&deps, get_file).unwrap();
+        let usage = load_usage(
+            &deps.storage,
+            &deps.api.canonical_address(&HumanAddr::from("writer")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(usage.blocks, 4);
+    }
+
+    #[test]
+    fn document_formats() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let photo = "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D".to_string();
+        let data = MetadataSchema {
+            name: "Formats".to_string(),
+            description: "testing sample".to_string(),
+            image: "".to_string(),
+            sources: vec![photo.clone()],
+            parent: photo.clone(),
+            refs: vec![photo.clone()],
+            owner: Some("alice".to_string()),
+            verified_credential: None,
+            licenses: Some(vec![SourceLicense {
+                source: photo.clone(),
+                license: "CC-BY-4.0".to_string(),
+                royalty_bps: 500,
+                payee: "alice".to_string(),
+            }]),
+            schema: None,
+        };
+        let add = HandleMsg::AddMetadata {
+            data: data.clone(),
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        let _ = handle(&mut deps, mock_env("writer", &[]), add).unwrap();
+        let cid = encode_metadata(&data).unwrap().cid().to_string();
+
+        let get_metadata = |format| QueryMsg::GetMetadata {
+            cid: cid.clone(),
+            path: "/".to_string(),
+            format,
+        };
+        match from_binary(&query(&deps, get_metadata(DocumentFormat::Typed)).unwrap()).unwrap() {
+            QueryAnswer::GetMetadata { data: Document::Typed(metadata) } => {
+                assert_eq!(
+                    metadata,
+                    Metadata {
+                        cid: cid.clone(),
+                        path: "/".to_string(),
+                        data: data.clone(),
+                    }
+                )
+            }
+            _ => panic!("unexpected answer"),
+        }
+        match from_binary(&query(&deps, get_metadata(DocumentFormat::DagJson)).unwrap()).unwrap() {
+            QueryAnswer::GetMetadata { data: Document::DagJson(json) } => {
+                assert!(json.contains(&format!(r#""parent":{{"/":"{}"}}"#, photo)))
+            }
+            _ => panic!("unexpected answer"),
+        }
+        // raw bytes are base64 in the JSON answer
+        let raw = query(&deps, get_metadata(DocumentFormat::Raw)).unwrap();
+        let bytes = encode_metadata(&data).unwrap().data().to_vec();
+        assert!(String::from_utf8(raw.as_slice().to_vec())
+            .unwrap()
+            .contains(&Binary::from(bytes).to_base64()));
+
+        let file = |encoding| HandleMsg::AddFile {
+            path: "/doc.txt".to_string(),
+            content: b"hello world\n".to_vec(),
+            mode: "0644".to_string(),
+            time: 1,
+            content_type: "text/plain".to_string(),
+            private: false,
+            encoding,
+        };
+        let mut cids = vec![];
+        for encoding in vec![FileEncoding::DagCbor, FileEncoding::Unixfs] {
+            let resp = handle(&mut deps, mock_env("writer", &[]), file(encoding)).unwrap();
+            match from_binary(&resp.data.unwrap()).unwrap() {
+                HandleAnswer::AddFile { cid } => cids.push(cid),
+                _ => panic!("unexpected answer"),
+            }
+        }
+        let get_file = |cid: &str, format| QueryMsg::GetFile {
+            cid: cid.to_string(),
+            path: "/doc.txt".to_string(),
+            format,
+        };
+        let typed = query(&deps, get_file(&cids[0], DocumentFormat::Typed)).unwrap();
+        match from_binary(&typed).unwrap() {
+            QueryAnswer::GetFile { data: Document::Typed(file) } => {
+                assert_eq!(file.cid, cids[0]);
+                assert_eq!(file.content, Binary::from(b"hello world\n".to_vec()));
+                assert_eq!(file.content_type, "text/plain");
+                assert_eq!(file.size, 12);
+            }
+            _ => panic!("unexpected answer"),
+        }
+        query(&deps, get_file(&cids[1], DocumentFormat::DagJson)).unwrap();
+        assert!(query(&deps, get_file(&cids[1], DocumentFormat::Typed)).is_err());
+    }
+
+    #[test]
+    fn add_dag() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let photo = "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D";
+        let dag_json = format!(
+            r#"{{"kind":"review","rating":5,"parent":{{"/":"{}"}},"sig":{{"/":{{"bytes":"aGVsbG8"}}}}}}"#,
+            photo
+        );
+        let add = HandleMsg::AddDag {
+            path: "/reviews".to_string(),
+            dag_json,
+            private: false,
+            link_policy: None,
+        };
+        let resp = handle(&mut deps, mock_env("writer", &[]), add).unwrap();
+
+        // the same document as canonical DAG-CBOR
+        let expected = Block::<DefaultParams>::encode(
+            DagCborCodec,
+            Code::Sha2_256,
+            &ipld!({
+                "kind": "review",
+                "rating": 5,
+                "parent": Ipld::Link(Cid::from_str(photo).unwrap()),
+                "sig": Ipld::Bytes(b"hello".to_vec()),
+            }),
+        )
+        .unwrap();
+        match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::AddDag { cid } => assert_eq!(cid, expected.cid().to_string()),
+            _ => panic!("unexpected answer"),
+        }
+        assert_eq!(resp.log[0], log("action", "add_dag"));
+
+        let get_metadata = QueryMsg::GetMetadata {
+            cid: expected.cid().to_string(),
+            path: "/reviews".to_string(),
+            format: DocumentFormat::Raw,
+        };
+        match from_binary(&query(&deps, get_metadata).unwrap()).unwrap() {
             QueryAnswer::GetMetadata { data } => {
+                assert_eq!(data, Document::Raw(Binary::from(expected.data())))
+            }
+            _ => panic!("unexpected answer"),
+        }
+        let index = may_load_block_index(&deps.storage, expected.cid()).unwrap().unwrap();
+        assert_eq!(index.path, "/reviews");
+
+        let invalid = HandleMsg::AddDag {
+            path: "/reviews".to_string(),
+            dag_json: "{\"kind\":".to_string(),
+            private: false,
+            link_policy: None,
+        };
+        assert!(handle(&mut deps, mock_env("writer", &[]), invalid).is_err());
+        // did:xdv owners have to be controlled by the writer
+        let unknown_owner = HandleMsg::AddDag {
+            path: "/reviews".to_string(),
+            dag_json: r#"{"owner":"did:xdv:unknown"}"#.to_string(),
+            private: false,
+            link_policy: None,
+        };
+        assert!(handle(&mut deps, mock_env("writer", &[]), unknown_owner).is_err());
+    }
+
+    #[test]
+    fn schemas() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let register = |name: &str, text: &str| HandleMsg::RegisterSchema {
+            name: name.to_string(),
+            ipld_schema_dsl: text.to_string(),
+        };
+        let review = register(
+            "review",
+            "type Review struct {\n  subject &Any\n  rating Int\n}",
+        );
+        let resp = handle(&mut deps, mock_env("curator", &[]), review.clone()).unwrap();
+        let review_cid = match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::RegisterSchema { cid } => cid,
+            _ => panic!("unexpected answer"),
+        };
+        assert_eq!(resp.log[0], log("action", "register_schema"));
+        assert!(handle(&mut deps, mock_env("curator", &[]), review).is_err());
+        let metadata = register(
+            "metadata",
+            r#"{"type":"object","properties":{"name":{"type":"string"}},"required":["name","parent"]}"#,
+        );
+        let resp = handle(&mut deps, mock_env("curator", &[]), metadata).unwrap();
+        let metadata_cid = match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::RegisterSchema { cid } => cid,
+            _ => panic!("unexpected answer"),
+        };
+        let unsupported = register("union", "type U union { | A string }");
+        assert!(handle(&mut deps, mock_env("curator", &[]), unsupported).is_err());
+
+        let list = |start_after, limit| QueryMsg::ListSchemas { start_after, limit };
+        match from_binary(&query(&deps, list(None, None)).unwrap()).unwrap() {
+            QueryAnswer::ListSchemas { schemas } => {
                 assert_eq!(
-                    data.len(),
-                    325,
+                    schemas,
+                    vec![
+                        SchemaInfo {
+                            name: "review".to_string(),
+                            cid: review_cid.clone(),
+                            owner: HumanAddr::from("curator"),
+                        },
+                        SchemaInfo {
+                            name: "metadata".to_string(),
+                            cid: metadata_cid.clone(),
+                            owner: HumanAddr::from("curator"),
+                        },
+                    ]
                 );
-                
+            }
+            _ => panic!("unexpected answer"),
+        }
+        let pages = vec![
+            (None, Some(1), &review_cid),
+            (Some(0), None, &metadata_cid),
+        ];
+        for (start_after, limit, expected) in pages {
+            match from_binary(&query(&deps, list(start_after, limit)).unwrap()).unwrap() {
+                QueryAnswer::ListSchemas { schemas } => {
+                    assert_eq!(schemas.len(), 1);
+                    assert_eq!(&schemas[0].cid, expected);
+                }
+                _ => panic!("unexpected answer"),
             }
         }
+
+        let photo = "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D";
+        let add_review = |rating: &str| HandleMsg::AddDag {
+            path: "/reviews".to_string(),
+            dag_json: format!(
+                r#"{{"schema":{{"/":"{}"}},"subject":{{"/":"{}"}},"rating":{}}}"#,
+                review_cid, photo, rating
+            ),
+            private: false,
+            link_policy: None,
+        };
+        handle(&mut deps, mock_env("writer", &[]), add_review("5")).unwrap();
+        let err = handle(&mut deps, mock_env("writer", &[]), add_review("\"five\"")).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Document does not match its schema at /rating: expected Int")
+        );
+
+        let add_metadata = |name: &str, schema: &str| HandleMsg::AddMetadata {
+            data: MetadataSchema {
+                name: name.to_string(),
+                description: "testing sample".to_string(),
+                image: "".to_string(),
+                sources: vec![],
+                parent: photo.to_string(),
+                refs: vec![],
+                owner: None,
+                verified_credential: None,
+                licenses: None,
+                schema: Some(schema.to_string()),
+            },
+            path: "/".to_string(),
+            signature: None,
+            issuer_key: None,
+            private: false,
+            link_policy: None,
+        };
+        handle(&mut deps, mock_env("writer", &[]), add_metadata("doc", &metadata_cid)).unwrap();
+        // metadata has no subject or rating
+        assert!(handle(&mut deps, mock_env("writer", &[]), add_metadata("doc", &review_cid)).is_err());
+        let unregistered = add_metadata("doc", "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o");
+        let err = handle(&mut deps, mock_env("writer", &[]), unregistered).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(
+                "Schema QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o is not registered"
+            )
+        );
+    }
+
+    #[test]
+    fn link_policies() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let msg = InitMsg { tag: "test".to_string() };
+        let _ = init(&mut deps, mock_env("creator", &[]), msg).unwrap();
+
+        let stored =
+            Block::<DefaultParams>::encode(RawCodec, Code::Sha2_256, &Ipld::Bytes(b"source".to_vec()))
+                .unwrap();
+        let put = HandleMsg::PutBlock {
+            cid: stored.cid().to_string(),
+            data: Binary::from(stored.data()),
+        };
+        let _ = handle(&mut deps, mock_env("writer", &[]), put).unwrap();
+        let stored = stored.cid().to_string();
+        let missing = "QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D";
+        let other = "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o";
+
+        let add = |parent: &str, sources: Vec<&str>, link_policy: Option<LinkPolicy>| {
+            HandleMsg::AddMetadata {
+                data: MetadataSchema {
+                    name: "linked".to_string(),
+                    description: "testing sample".to_string(),
+                    image: "".to_string(),
+                    sources: sources.into_iter().map(String::from).collect(),
+                    parent: parent.to_string(),
+                    refs: vec![],
+                    owner: None,
+                    verified_credential: None,
+                    licenses: None,
+                    schema: None,
+                },
+                path: "/".to_string(),
+                signature: None,
+                issuer_key: None,
+                private: false,
+                link_policy,
+            }
+        };
+        // by default any CID can be linked
+        let _ = handle(&mut deps, mock_env("writer", &[]), add(missing, vec![], None)).unwrap();
+
+        let local_only = add(missing, vec![&stored, other, missing], Some(LinkPolicy::LocalOnly));
+        let err = handle(&mut deps, mock_env("writer", &[]), local_only).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!(
+                "Links are not stored in this contract: {}, {}",
+                missing, other
+            ))
+        );
+        let local_only = add(&stored, vec![&stored], Some(LinkPolicy::LocalOnly));
+        let _ = handle(&mut deps, mock_env("writer", &[]), local_only).unwrap();
+        // migrated blocks have data but no index entry
+        let migrated =
+            Block::<DefaultParams>::encode(RawCodec, Code::Sha2_256, &Ipld::Bytes(b"legacy".to_vec()))
+                .unwrap();
+        save_block_data(&mut deps.storage, migrated.cid(), migrated.data());
+        let migrated = migrated.cid().to_string();
+        let local_only = add(&stored, vec![&migrated], Some(LinkPolicy::LocalOnly));
+        let _ = handle(&mut deps, mock_env("writer", &[]), local_only).unwrap();
+        let err = handle(&mut deps, mock_env("writer", &[]), add(&stored, vec!["not a cid"], None))
+            .unwrap_err();
+        assert!(matches!(err, StdError::ParseErr { .. }));
+
+        let raw_only = LinkPolicy::AllowCodecs(vec!["raw".to_string()]);
+        let _ = handle(&mut deps, mock_env("writer", &[]), add(&stored, vec![], Some(raw_only.clone())))
+            .unwrap();
+        let err = handle(&mut deps, mock_env("writer", &[]), add(missing, vec![], Some(raw_only)))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!("Codec dag-pb of link {} is not allowed", missing))
+        );
+
+        // a contract wide policy applies to every write, whatever the write asks for
+        let set_rules = HandleMsg::SetValidationRules {
+            rules: ValidationRules {
+                link_policy: LinkPolicy::LocalOnly,
+                ..ValidationRules::default()
+            },
+        };
+        let _ = handle(&mut deps, mock_env("creator", &[]), set_rules).unwrap();
+        let any = add(missing, vec![], Some(LinkPolicy::Any));
+        assert!(handle(&mut deps, mock_env("writer", &[]), any).is_err());
+        let dag = |dag_json: String| HandleMsg::AddDag {
+            path: "/reviews".to_string(),
+            dag_json,
+            private: false,
+            link_policy: None,
+        };
+        let err = handle(
+            &mut deps,
+            mock_env("writer", &[]),
+            dag(format!(r#"{{"subject":{{"/":"{}"}}}}"#, other)),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!("Links are not stored in this contract: {}", other))
+        );
+        let _ = handle(
+            &mut deps,
+            mock_env("writer", &[]),
+            dag(format!(r#"{{"subject":{{"/":"{}"}}}}"#, stored)),
+        )
+        .unwrap();
     }
 
     // fn get_file() {
@@ -0,0 +1,146 @@
+use std::convert::TryFrom;
+
+use cosmwasm_std::{StdError, StdResult};
+use k256::ecdsa::signature::Verifier;
+use rand_core::{CryptoRng, Error as RngError, RngCore};
+use rsa::{Hash, PaddingScheme, PublicKey, RSAPublicKey};
+use sha2::{Digest, Sha256};
+
+use crate::state::SignatureAlgo;
+
+/// Verifies `sig` over `message` with the given public key.
+///
+/// - secp256k1: SEC1 encoded key (33 or 65 bytes), 64 byte `r || s` ECDSA signature over SHA-256
+/// - ed25519: 32 byte key, 64 byte signature over the raw message
+/// - RSA: PKCS#8 or PKCS#1 DER encoded key, signature over SHA-256
+pub fn verify_signature(
+    algo: &SignatureAlgo,
+    pubkey: &[u8],
+    message: &[u8],
+    sig: &[u8],
+) -> StdResult<()> {
+    match algo {
+        SignatureAlgo::Secp256k1 => verify_secp256k1(pubkey, message, sig),
+        SignatureAlgo::Ed25519 => verify_ed25519(pubkey, message, sig),
+        SignatureAlgo::RsaPkcs1v15 => verify_rsa(
+            pubkey,
+            message,
+            sig,
+            PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)),
+        ),
+        SignatureAlgo::RsaPss => verify_rsa(
+            pubkey,
+            message,
+            sig,
+            PaddingScheme::new_pss::<Sha256, _>(NoRng),
+        ),
+    }
+}
+
+/// Checks that `pubkey` is a well formed key for `algo`
+pub fn check_public_key(algo: &SignatureAlgo, pubkey: &[u8]) -> StdResult<()> {
+    let valid = match algo {
+        SignatureAlgo::Secp256k1 => k256::ecdsa::VerifyingKey::from_sec1_bytes(pubkey).is_ok(),
+        SignatureAlgo::Ed25519 => ed25519_zebra::VerificationKey::try_from(pubkey).is_ok(),
+        SignatureAlgo::RsaPkcs1v15 | SignatureAlgo::RsaPss => {
+            RSAPublicKey::from_pkcs8(pubkey).is_ok() || RSAPublicKey::from_pkcs1(pubkey).is_ok()
+        }
+    };
+    if !valid {
+        return Err(StdError::generic_err("Invalid public key for the given algorithm"));
+    }
+    Ok(())
+}
+
+fn verify_secp256k1(pubkey: &[u8], message: &[u8], sig: &[u8]) -> StdResult<()> {
+    let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(pubkey)
+        .map_err(|_| StdError::generic_err("Invalid secp256k1 public key"))?;
+    let signature = k256::ecdsa::Signature::try_from(sig)
+        .map_err(|_| StdError::generic_err("Invalid secp256k1 signature"))?;
+
+    key.verify(message, &signature)
+        .map_err(|_| StdError::generic_err("secp256k1 signature verification failed"))
+}
+
+fn verify_ed25519(pubkey: &[u8], message: &[u8], sig: &[u8]) -> StdResult<()> {
+    let key = ed25519_zebra::VerificationKey::try_from(pubkey)
+        .map_err(|_| StdError::generic_err("Invalid ed25519 public key"))?;
+    let signature = ed25519_zebra::Signature::try_from(sig)
+        .map_err(|_| StdError::generic_err("Invalid ed25519 signature"))?;
+
+    key.verify(&signature, message)
+        .map_err(|_| StdError::generic_err("ed25519 signature verification failed"))
+}
+
+fn verify_rsa(pubkey: &[u8], message: &[u8], sig: &[u8], padding: PaddingScheme) -> StdResult<()> {
+    let key = RSAPublicKey::from_pkcs8(pubkey)
+        .or_else(|_| RSAPublicKey::from_pkcs1(pubkey))
+        .map_err(|_| StdError::generic_err("Invalid RSA public key"))?;
+    let hashed = Sha256::digest(message);
+
+    key.verify(padding, &hashed, sig)
+        .map_err(|_| StdError::generic_err("RSA signature verification failed"))
+}
+
+/// PSS verification never draws randomness, but the padding scheme still asks for a source.
+struct NoRng;
+
+impl RngCore for NoRng {
+    fn next_u32(&mut self) -> u32 {
+        0
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        0
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for b in dest.iter_mut() {
+            *b = 0;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), RngError> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for NoRng {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::signature::Signer;
+
+    #[test]
+    fn secp256k1_roundtrip() {
+        let signing_key = k256::ecdsa::SigningKey::from_bytes(&[7u8; 32]).unwrap();
+        let pubkey = signing_key.verifying_key().to_bytes();
+        let sig: k256::ecdsa::Signature = signing_key.sign(b"ancon");
+
+        verify_signature(&SignatureAlgo::Secp256k1, &pubkey, b"ancon", sig.as_ref()).unwrap();
+        verify_signature(&SignatureAlgo::Secp256k1, &pubkey, b"other", sig.as_ref()).unwrap_err();
+    }
+
+    #[test]
+    fn ed25519_roundtrip() {
+        let signing_key = ed25519_zebra::SigningKey::from([9u8; 32]);
+        let pubkey: [u8; 32] = ed25519_zebra::VerificationKey::from(&signing_key).into();
+        let sig: [u8; 64] = signing_key.sign(b"ancon").into();
+
+        verify_signature(&SignatureAlgo::Ed25519, &pubkey, b"ancon", &sig).unwrap();
+        verify_signature(&SignatureAlgo::Ed25519, &pubkey, b"other", &sig).unwrap_err();
+    }
+
+    #[test]
+    fn rsa_pkcs1v15_vector() {
+        // 1024 bit PKCS#1 public key and its SHA-256 PKCS#1 v1.5 signature over "ancon"
+        let pubkey = hex::decode("30818902818100d89593e07fc96b4dfb4cad712e4e957a30a9563abdf63ffa46f197ffc9ecbb02ee888b6733839ccf748de26db8b2c5e24ecb61443de9111ec88659dd3c55e7bfb51943e67fd2c0e732aa1e58e5e36cf638e991af8ba0d454b45b3fce379f78e289fbdfe11edd1b78ca324aceb9c0c3f1c5e768f5c3d9ebde3e9301406537731d0203010001").unwrap();
+        let sig = hex::decode("1576d9a5c717edd69c845b12cd9a6a6a261c9b487f322ca0757c4cb0e60fc714655d7dd316596f23fc2c5e542b19baeccc1e64018f9bf1216087ae2bef1fb5306d1b9fa09d976b1489526565b2e61b6afa3e2cc199dbc66091d99fac8a8352fce9fa617b15739886f6e2989c3f9a8aaac3b548fcfd3fec693b38d5421e425471").unwrap();
+
+        verify_signature(&SignatureAlgo::RsaPkcs1v15, &pubkey, b"ancon", &sig).unwrap();
+        verify_signature(&SignatureAlgo::RsaPkcs1v15, &pubkey, b"other", &sig).unwrap_err();
+        verify_signature(&SignatureAlgo::RsaPss, &pubkey, b"ancon", &sig).unwrap_err();
+    }
+}
@@ -0,0 +1,165 @@
+use cosmwasm_std::{Api, HumanAddr, StdError, StdResult};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::state::{DidRecord, SignatureAlgo, VerificationMethod};
+
+pub const DID_PREFIX: &str = "did:xdv:";
+pub const DID_CONTEXT: &str = "https://www.w3.org/ns/did/v1";
+
+/// DID document as returned by `ResolveDid`. Controllers are the chain addresses allowed to update it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DidDocument {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    pub id: String,
+    pub controller: Vec<HumanAddr>,
+    #[serde(rename = "verificationMethod")]
+    pub verification_method: Vec<DocumentVerificationMethod>,
+    pub authentication: Vec<String>,
+    pub service: Vec<DocumentService>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DocumentVerificationMethod {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub method_type: String,
+    pub controller: String,
+    #[serde(rename = "publicKeyHex")]
+    pub public_key_hex: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DocumentService {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub service_type: String,
+    #[serde(rename = "serviceEndpoint")]
+    pub service_endpoint: String,
+}
+
+/// Checks that `did` is a `did:xdv` identifier with a non empty method specific id
+pub fn validate_did(did: &str) -> StdResult<()> {
+    let id = did.strip_prefix(DID_PREFIX).ok_or_else(|| {
+        StdError::generic_err(format!("Only {} identifiers can be registered", DID_PREFIX))
+    })?;
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' || c == ':');
+    if !valid {
+        return Err(StdError::generic_err(format!("Invalid DID {}", did)));
+    }
+    Ok(())
+}
+
+/// Expands `key-1` or `#key-1` to `did:xdv:...#key-1`; full ids must belong to `did`
+pub fn qualify_id(did: &str, id: &str) -> StdResult<String> {
+    let fragment = match id.find('#') {
+        Some(0) => &id[1..],
+        Some(pos) if &id[..pos] == did => &id[pos + 1..],
+        Some(_) => {
+            return Err(StdError::generic_err(format!(
+                "{} does not belong to {}",
+                id, did
+            )))
+        }
+        None => id,
+    };
+    if fragment.is_empty() {
+        return Err(StdError::generic_err("Identifier fragment cannot be empty"));
+    }
+    Ok(format!("{}#{}", did, fragment))
+}
+
+/// Verification method type used in the document for each signature algorithm
+pub fn method_type(algo: &SignatureAlgo) -> &'static str {
+    match algo {
+        SignatureAlgo::Secp256k1 => "EcdsaSecp256k1VerificationKey2019",
+        SignatureAlgo::Ed25519 => "Ed25519VerificationKey2018",
+        SignatureAlgo::RsaPkcs1v15 | SignatureAlgo::RsaPss => "RsaVerificationKey2018",
+    }
+}
+
+/// Keys to try for a JWS issued by `did`: the `kid` one if given, otherwise all of them
+pub fn issuer_methods<'a>(
+    did: &str,
+    record: &'a DidRecord,
+    kid: Option<&str>,
+) -> StdResult<Vec<&'a VerificationMethod>> {
+    match kid {
+        Some(kid) => {
+            let kid = qualify_id(did, kid)?;
+            Ok(record
+                .verification_methods
+                .iter()
+                .filter(|m| m.id == kid)
+                .collect())
+        }
+        None => Ok(record.verification_methods.iter().collect()),
+    }
+}
+
+pub fn to_document<A: Api>(api: &A, did: &str, record: &DidRecord) -> StdResult<DidDocument> {
+    let controller = record
+        .controllers
+        .iter()
+        .map(|c| api.human_address(c))
+        .collect::<StdResult<Vec<_>>>()?;
+    let verification_method: Vec<DocumentVerificationMethod> = record
+        .verification_methods
+        .iter()
+        .map(|m| DocumentVerificationMethod {
+            id: m.id.clone(),
+            method_type: method_type(&m.algo).to_string(),
+            controller: did.to_string(),
+            public_key_hex: hex::encode(m.public_key.as_slice()),
+        })
+        .collect();
+    let authentication = verification_method.iter().map(|m| m.id.clone()).collect();
+    let service = record
+        .services
+        .iter()
+        .map(|s| DocumentService {
+            id: s.id.clone(),
+            service_type: s.service_type.clone(),
+            service_endpoint: s.endpoint.clone(),
+        })
+        .collect();
+
+    Ok(DidDocument {
+        context: vec![DID_CONTEXT.to_string()],
+        id: did.to_string(),
+        controller,
+        verification_method,
+        authentication,
+        service,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn did_syntax() {
+        validate_did("did:xdv:0xabc-1").unwrap();
+        validate_did("did:ethr:0xabc").unwrap_err();
+        validate_did("did:xdv:").unwrap_err();
+        validate_did("did:xdv:a/b").unwrap_err();
+    }
+
+    #[test]
+    fn qualified_ids() {
+        let did = "did:xdv:alice";
+        assert_eq!(qualify_id(did, "key-1").unwrap(), "did:xdv:alice#key-1");
+        assert_eq!(qualify_id(did, "#key-1").unwrap(), "did:xdv:alice#key-1");
+        assert_eq!(
+            qualify_id(did, "did:xdv:alice#key-1").unwrap(),
+            "did:xdv:alice#key-1"
+        );
+        qualify_id(did, "did:xdv:bob#key-1").unwrap_err();
+        qualify_id(did, "#").unwrap_err();
+    }
+}
@@ -0,0 +1,97 @@
+use cosmwasm_std::{Binary, StdError, StdResult};
+use libipld::{
+    block::Block, codec::Codec, ipld::Ipld, json::DagJsonCodec, store::DefaultParams, Cid,
+};
+
+use crate::events::codec_name;
+use crate::state::{File, MetadataSchema, SourceLicense};
+
+/// The IPLD DAG-JSON encoding of a block: links as `{"/": cid}` and bytes as `{"/": {"bytes": ..}}`
+pub fn to_dag_json(block: &Block<DefaultParams>) -> StdResult<String> {
+    let node = block
+        .ipld()
+        .map_err(|e| StdError::parse_err(codec_name(block.cid().codec()), e))?;
+    let json = DagJsonCodec
+        .encode(&node)
+        .map_err(|e| StdError::serialize_err("DAG-JSON", e))?;
+    String::from_utf8(json).map_err(|e| StdError::serialize_err("DAG-JSON", e))
+}
+
+/// Reads back the map `AddFile` stores with the DAG-CBOR encoding
+pub fn decode_file(cid: &Cid, node: &Ipld) -> StdResult<File> {
+    let content = match field(node, "content")? {
+        Ipld::Bytes(content) => content.clone(),
+        _ => return Err(invalid("content")),
+    };
+    Ok(File {
+        content_type: string_field(node, "type")?,
+        cid: cid.to_string(),
+        path: string_field(node, "path")?,
+        size: content.len() as u64,
+        content: Binary::from(content),
+        mode: string_field(node, "mode")?,
+    })
+}
+
+/// Reads back a metadata block, with links as CID strings. `licenses` are the decoded blocks that the
+/// `licenses` links point to, in order.
+pub fn decode_metadata(
+    node: &Ipld,
+    licenses: Option<Vec<SourceLicense>>,
+) -> StdResult<MetadataSchema> {
+    Ok(MetadataSchema {
+        name: string_field(node, "name")?,
+        description: string_field(node, "description")?,
+        image: string_field(node, "image")?,
+        sources: link_list(node, "sources")?,
+        parent: link(field(node, "parent")?, "parent")?,
+        refs: link_list(node, "refs")?,
+        owner: optional_string(node, "owner")?,
+        verified_credential: optional_string(node, "verifiedCredential")?,
+        licenses,
+        schema: match field(node, "schema") {
+            Ok(schema) => Some(link(schema, "schema")?),
+            Err(_) => None,
+        },
+    })
+}
+
+fn field<'a>(node: &'a Ipld, key: &str) -> StdResult<&'a Ipld> {
+    match node {
+        Ipld::Map(map) => map.get(key).ok_or_else(|| invalid(key)),
+        _ => Err(StdError::generic_err("Document is not a map")),
+    }
+}
+
+fn string_field(node: &Ipld, key: &str) -> StdResult<String> {
+    match field(node, key)? {
+        Ipld::String(value) => Ok(value.clone()),
+        _ => Err(invalid(key)),
+    }
+}
+
+fn optional_string(node: &Ipld, key: &str) -> StdResult<Option<String>> {
+    match field(node, key) {
+        Ok(Ipld::String(value)) => Ok(Some(value.clone())),
+        Ok(_) => Err(invalid(key)),
+        Err(_) => Ok(None),
+    }
+}
+
+fn link(value: &Ipld, key: &str) -> StdResult<String> {
+    match value {
+        Ipld::Link(cid) => Ok(cid.to_string()),
+        _ => Err(invalid(key)),
+    }
+}
+
+fn link_list(node: &Ipld, key: &str) -> StdResult<Vec<String>> {
+    match field(node, key)? {
+        Ipld::List(items) => items.iter().map(|item| link(item, key)).collect(),
+        _ => Err(invalid(key)),
+    }
+}
+
+fn invalid(key: &str) -> StdError {
+    StdError::generic_err(format!("Document has no valid {}", key))
+}
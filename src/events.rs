@@ -0,0 +1,76 @@
+use cosmwasm_std::{log, Coin, HumanAddr, LogAttribute};
+use libipld::Cid;
+
+/// Value logged in place of attributes of a write marked `private`
+pub const REDACTED: &str = "redacted";
+
+/// Multicodec name of a CID codec, or its hex code when it is not one we store
+pub fn codec_name(codec: u64) -> String {
+    match codec {
+        0x55 => "raw".to_string(),
+        0x70 => "dag-pb".to_string(),
+        0x71 => "dag-cbor".to_string(),
+        0x0129 => "dag-json".to_string(),
+        other => format!("0x{:x}", other),
+    }
+}
+
+/// Coins as `<amount><denom>` joined by commas, like the bank module logs them
+pub fn coins_attr(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(|coin| format!("{}{}", coin.amount, coin.denom))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Attributes shared by every block write. `path` and `owner` are redacted for private writes;
+/// `cid`, `codec` and `size` are always public since the CID is returned to the caller anyway.
+pub fn write_log(
+    action: &str,
+    cid: &Cid,
+    size: usize,
+    path: &str,
+    owner: &HumanAddr,
+    private: bool,
+) -> Vec<LogAttribute> {
+    vec![
+        log("action", action),
+        log("cid", cid),
+        log("codec", codec_name(cid.codec())),
+        log("size", size),
+        log("path", redact(path, private)),
+        log("owner", redact(owner.as_str(), private)),
+        log("private", private),
+    ]
+}
+
+pub fn redact(value: &str, private: bool) -> &str {
+    if private {
+        REDACTED
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn redacts_private_writes() {
+        let cid = Cid::from_str("QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D").unwrap();
+        let owner = HumanAddr::from("writer");
+
+        let public = write_log("add_file", &cid, 10, "/a.txt", &owner, false);
+        assert_eq!(public[2], log("codec", "dag-pb"));
+        assert_eq!(public[4], log("path", "/a.txt"));
+        assert_eq!(public[5], log("owner", "writer"));
+
+        let private = write_log("add_file", &cid, 10, "/a.txt", &owner, true);
+        assert_eq!(private[1], log("cid", cid));
+        assert_eq!(private[4], log("path", REDACTED));
+        assert_eq!(private[5], log("owner", REDACTED));
+    }
+}
@@ -0,0 +1,138 @@
+use cosmwasm_std::{Coin, StdError, StdResult, Uint128};
+
+use crate::state::FeeRate;
+
+/// What `ops` writes of `bytes` in total cost in each configured denom: the flat fee per op plus the
+/// per byte fee
+pub fn quote_fees(rates: &[FeeRate], ops: u64, bytes: u64) -> Vec<Coin> {
+    rates
+        .iter()
+        .map(|rate| Coin {
+            denom: rate.denom.clone(),
+            amount: Uint128(
+                rate.per_byte
+                    .u128()
+                    .saturating_mul(bytes as u128)
+                    .saturating_add(rate.flat.u128().saturating_mul(ops as u128)),
+            ),
+        })
+        .collect()
+}
+
+/// The fee charged for a write: the quote in the first denom `sent` pays, or nothing when no fees are set
+pub fn check_fee(rates: &[FeeRate], sent: &[Coin], ops: u64, bytes: u64) -> StdResult<Vec<Coin>> {
+    let quotes = quote_fees(rates, ops, bytes);
+    if quotes.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let paid = quotes.iter().find(|quote| {
+        sent.iter()
+            .any(|coin| coin.denom == quote.denom && coin.amount.u128() >= quote.amount.u128())
+    });
+    if paid.is_none() {
+        let options: Vec<String> = quotes
+            .iter()
+            .map(|q| format!("{}{}", q.amount, q.denom))
+            .collect();
+        return Err(StdError::generic_err(format!(
+            "Writing {} bytes requires a fee of {}",
+            bytes,
+            options.join(" or ")
+        )));
+    }
+    Ok(paid.into_iter().cloned().collect())
+}
+
+/// Adds `coins` to a per denom balance, failing if a denom would overflow
+pub fn add_coins(balance: &mut Vec<Coin>, coins: &[Coin]) -> StdResult<()> {
+    for coin in coins {
+        match balance.iter_mut().find(|c| c.denom == coin.denom) {
+            Some(existing) => {
+                let amount = existing
+                    .amount
+                    .u128()
+                    .checked_add(coin.amount.u128())
+                    .ok_or_else(|| {
+                        StdError::generic_err(format!("Collected {} fees overflow", coin.denom))
+                    })?;
+                existing.amount = Uint128(amount);
+            }
+            None => balance.push(coin.clone()),
+        }
+    }
+    Ok(())
+}
+
+/// Takes `coins` out of a per denom balance, failing if any denom falls short
+pub fn sub_coins(balance: &mut Vec<Coin>, coins: &[Coin]) -> StdResult<()> {
+    for coin in coins {
+        let existing = balance
+            .iter_mut()
+            .find(|c| c.denom == coin.denom)
+            .filter(|c| c.amount.u128() >= coin.amount.u128())
+            .ok_or_else(|| {
+                StdError::generic_err(format!("Not enough {} fees collected", coin.denom))
+            })?;
+        existing.amount = Uint128(existing.amount.u128() - coin.amount.u128());
+    }
+    balance.retain(|c| c.amount.u128() > 0);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::coins;
+
+    #[test]
+    fn quotes_and_checks() {
+        let rates = vec![
+            FeeRate {
+                denom: "uscrt".to_string(),
+                flat: Uint128(100),
+                per_byte: Uint128(2),
+            },
+            FeeRate {
+                denom: "uxdv".to_string(),
+                flat: Uint128(1),
+                per_byte: Uint128(0),
+            },
+        ];
+        assert_eq!(
+            quote_fees(&rates, 1, 10),
+            vec![coins(120, "uscrt")[0].clone(), coins(1, "uxdv")[0].clone()]
+        );
+        assert_eq!(
+            quote_fees(&rates, 3, 10),
+            vec![coins(320, "uscrt")[0].clone(), coins(3, "uxdv")[0].clone()]
+        );
+
+        assert_eq!(
+            check_fee(&rates, &coins(150, "uscrt"), 1, 10).unwrap(),
+            coins(120, "uscrt")
+        );
+        assert_eq!(
+            check_fee(&rates, &coins(1, "uxdv"), 1, 10_000).unwrap(),
+            coins(1, "uxdv")
+        );
+        check_fee(&rates, &coins(119, "uscrt"), 1, 10).unwrap_err();
+        check_fee(&rates, &coins(120, "uscrt"), 2, 10).unwrap_err();
+        check_fee(&rates, &[], 1, 10).unwrap_err();
+        assert_eq!(check_fee(&[], &coins(5, "uscrt"), 1, 10).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn balances() {
+        let mut balance = vec![];
+        add_coins(&mut balance, &coins(5, "uscrt")).unwrap();
+        add_coins(&mut balance, &coins(5, "uscrt")).unwrap();
+        assert_eq!(balance, coins(10, "uscrt"));
+        add_coins(&mut balance, &coins(u128::MAX, "uscrt")).unwrap_err();
+
+        sub_coins(&mut balance, &coins(11, "uscrt")).unwrap_err();
+        sub_coins(&mut balance, &coins(1, "uxdv")).unwrap_err();
+        sub_coins(&mut balance, &coins(10, "uscrt")).unwrap();
+        assert!(balance.is_empty());
+    }
+}
@@ -1,12 +1,28 @@
+pub mod car;
 pub mod contract;
+pub mod crypto;
+pub mod did;
+pub mod documents;
+pub mod events;
+pub mod fees;
+pub mod license;
+pub mod migrations;
 pub mod msg;
+pub mod nft;
+pub mod placeholders;
+pub mod quota;
+pub mod revocation;
+pub mod schema;
 pub mod state;
+pub mod unixfs;
+pub mod validation;
+pub mod vc;
 
 #[cfg(target_arch = "wasm32")]
 mod wasm {
     use super::contract;
     use cosmwasm_std::{
-        do_handle, do_init, do_query, ExternalApi, ExternalQuerier, ExternalStorage,
+        do_handle, do_init, do_migrate, do_query, ExternalApi, ExternalQuerier, ExternalStorage,
     };
 
     #[no_mangle]
@@ -27,6 +43,15 @@ mod wasm {
         )
     }
 
+    #[no_mangle]
+    extern "C" fn migrate(env_ptr: u32, msg_ptr: u32) -> u32 {
+        do_migrate(
+            &contract::migrate::<ExternalStorage, ExternalApi, ExternalQuerier>,
+            env_ptr,
+            msg_ptr,
+        )
+    }
+
     #[no_mangle]
     extern "C" fn query(msg_ptr: u32) -> u32 {
         do_query(
@@ -0,0 +1,160 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{Api, HumanAddr, StdError, StdResult};
+use libipld::{
+    block::Block, cbor::DagCborCodec, cid::multihash::Code, ipld, ipld::Ipld, store::DefaultParams,
+    Cid,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::state::SourceLicense;
+
+/// Royalties are expressed in basis points of the sale price
+pub const MAX_ROYALTY_BPS: u16 = 10_000;
+
+/// A license found while walking the lineage of a metadata block
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LicenseTerm {
+    /// Metadata block that declared the license
+    pub declared_in: String,
+    pub source: String,
+    pub license: String,
+    pub royalty_bps: u16,
+    pub payee: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Royalty {
+    pub payee: String,
+    pub royalty_bps: u32,
+}
+
+/// Encodes a license as `{ "license", "payee", "royaltyBps", "source": <link> }`.
+/// A license given as a CID is stored as a link to the license text.
+pub fn encode_license(
+    license: &SourceLicense,
+    sources: &[String],
+) -> StdResult<Block<DefaultParams>> {
+    if !sources.contains(&license.source) {
+        return Err(StdError::generic_err(format!(
+            "License source {} is not one of the metadata sources",
+            license.source
+        )));
+    }
+    if license.royalty_bps > MAX_ROYALTY_BPS {
+        return Err(StdError::generic_err(format!(
+            "Royalty cannot exceed {} basis points",
+            MAX_ROYALTY_BPS
+        )));
+    }
+    if license.license.is_empty() || license.payee.is_empty() {
+        return Err(StdError::generic_err("License and payee are required"));
+    }
+
+    let source = Cid::from_str(&license.source).map_err(|e| StdError::parse_err("Cid", e))?;
+    let terms = match Cid::from_str(&license.license) {
+        Ok(cid) => Ipld::Link(cid),
+        Err(_) => Ipld::String(license.license.clone()),
+    };
+
+    Block::<DefaultParams>::encode(
+        DagCborCodec,
+        Code::Sha2_256,
+        &ipld!({
+            "source": Ipld::Link(source),
+            "license": terms,
+            "royaltyBps": license.royalty_bps,
+            "payee": license.payee.clone(),
+        }),
+    )
+    .map_err(|e| StdError::serialize_err("Ipld", e))
+}
+
+/// Payees are DIDs or addresses of this chain
+pub fn check_payee<A: Api>(api: &A, payee: &str) -> StdResult<()> {
+    if payee.starts_with("did:") {
+        return Ok(());
+    }
+    api.canonical_address(&HumanAddr::from(payee))
+        .map(|_| ())
+        .map_err(|_| {
+            StdError::generic_err(format!("Payee {} is not a DID or a valid address", payee))
+        })
+}
+
+fn field<'a>(node: &'a Ipld, key: &str) -> Option<&'a Ipld> {
+    match node {
+        Ipld::Map(map) => map.get(key),
+        _ => None,
+    }
+}
+
+/// Reads a license block back, or `None` if it is not one
+pub fn decode_license(declared_in: &Cid, node: &Ipld) -> Option<LicenseTerm> {
+    let source = match field(node, "source") {
+        Some(Ipld::Link(cid)) => cid.to_string(),
+        _ => return None,
+    };
+    let license = match field(node, "license") {
+        Some(Ipld::Link(cid)) => cid.to_string(),
+        Some(Ipld::String(id)) => id.clone(),
+        _ => return None,
+    };
+    let royalty_bps = match field(node, "royaltyBps") {
+        Some(Ipld::Integer(bps)) if *bps >= 0 && *bps <= MAX_ROYALTY_BPS as i128 => *bps as u16,
+        _ => return None,
+    };
+    let payee = match field(node, "payee") {
+        Some(Ipld::String(payee)) => payee.clone(),
+        _ => return None,
+    };
+
+    Some(LicenseTerm {
+        declared_in: declared_in.to_string(),
+        source,
+        license,
+        royalty_bps,
+        payee,
+    })
+}
+
+/// Links of a metadata block that carry licenses, and the blocks it derives from
+pub fn license_links(node: &Ipld) -> Vec<Cid> {
+    links_at(node, "licenses")
+}
+
+pub fn lineage_links(node: &Ipld) -> Vec<Cid> {
+    let mut links = links_at(node, "parent");
+    links.extend(links_at(node, "sources"));
+    links
+}
+
+fn links_at(node: &Ipld, key: &str) -> Vec<Cid> {
+    match field(node, key) {
+        Some(Ipld::Link(cid)) => vec![*cid],
+        Some(Ipld::List(items)) => items
+            .iter()
+            .filter_map(|item| match item {
+                Ipld::Link(cid) => Some(*cid),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Sums royalties per payee, in the order payees were first seen
+pub fn royalties(terms: &[LicenseTerm]) -> Vec<Royalty> {
+    let mut royalties: Vec<Royalty> = vec![];
+    for term in terms {
+        match royalties.iter_mut().find(|r| r.payee == term.payee) {
+            Some(royalty) => royalty.royalty_bps += term.royalty_bps as u32,
+            None => royalties.push(Royalty {
+                payee: term.payee.clone(),
+                royalty_bps: term.royalty_bps as u32,
+            }),
+        }
+    }
+    royalties
+}
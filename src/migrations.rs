@@ -0,0 +1,160 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{Api, Extern, Querier, StdError, StdResult, Storage};
+use libipld::{block::Block, store::DefaultParams, Cid};
+
+use crate::msg::{LegacyKey, LegacyKind};
+use crate::state::{
+    config, config_read, may_load_block_data, may_load_block_index, move_legacy_index,
+    save_block_data, save_block_index, save_path, take_legacy_block, BlockIndex, PREFIX_FILES,
+    PREFIX_METADATA,
+};
+
+/// Version recorded for stores written before versions were tracked
+pub const UNVERSIONED: &str = "0.0.0";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Migration {
+    /// Writes back `State` so fields added since carry their defaults
+    UpgradeState,
+    /// Moves `"<cid>::<path>"` keys into the binary namespaces
+    RekeyStorage,
+    /// Indexes moved blocks under the writer given with their key
+    BackfillIndexes,
+}
+
+/// Every migration with the version that introduced it, in the order they run
+pub const MIGRATIONS: &[(&str, Migration)] = &[
+    ("0.2.0", Migration::UpgradeState),
+    ("0.2.0", Migration::RekeyStorage),
+    ("0.2.0", Migration::BackfillIndexes),
+];
+
+/// Migrations needed to go from version `from` to `to`
+pub fn pending(from: &str, to: &str) -> StdResult<Vec<Migration>> {
+    let (from, to) = (parse_version(from)?, parse_version(to)?);
+    if from > to {
+        return Err(StdError::generic_err("Cannot migrate to an older version"));
+    }
+
+    let mut pending = vec![];
+    for (version, migration) in MIGRATIONS {
+        let version = parse_version(version)?;
+        if from < version && version <= to {
+            pending.push(*migration);
+        }
+    }
+    Ok(pending)
+}
+
+/// `major.minor.patch`, compared numerically
+pub fn parse_version(version: &str) -> StdResult<(u64, u64, u64)> {
+    let parts = version
+        .split('.')
+        .map(|part| part.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| StdError::parse_err("version", e))?;
+    match parts.as_slice() {
+        [major, minor, patch] => Ok((*major, *minor, *patch)),
+        _ => Err(StdError::generic_err(format!(
+            "Invalid version {}",
+            version
+        ))),
+    }
+}
+
+/// Runs one migration, returning how many legacy keys it moved
+pub fn run<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    migration: Migration,
+    keys: &[LegacyKey],
+) -> StdResult<u32> {
+    match migration {
+        Migration::UpgradeState => {
+            let state = config_read(&deps.storage).load()?;
+            config(&mut deps.storage).save(&state)?;
+            Ok(0)
+        }
+        Migration::RekeyStorage => {
+            let mut moved = 0;
+            for key in keys {
+                if rekey(&mut deps.storage, key)? {
+                    moved += 1;
+                }
+            }
+            Ok(moved)
+        }
+        Migration::BackfillIndexes => {
+            for key in keys {
+                backfill_index(deps, key)?;
+            }
+            Ok(0)
+        }
+    }
+}
+
+/// Moves one legacy key, returning false when nothing is stored under it
+pub fn rekey<S: Storage>(storage: &mut S, key: &LegacyKey) -> StdResult<bool> {
+    let cid = Cid::from_str(&key.cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    let data = match take_legacy_block(storage, &key.cid, &key.path)? {
+        Some(data) => data,
+        None => return Ok(false),
+    };
+    let block =
+        Block::<DefaultParams>::new(cid, data).map_err(|e| StdError::parse_err("Block", e))?;
+    let namespace = match key.kind {
+        LegacyKind::File => PREFIX_FILES,
+        LegacyKind::Metadata => PREFIX_METADATA,
+    };
+
+    save_block_data(storage, &cid, block.data());
+    save_path(storage, namespace, &cid, &key.path)?;
+    move_legacy_index(storage, &cid)?;
+    Ok(true)
+}
+
+/// Blocks written before the index existed have no owner; the key may name the address that wrote them
+pub fn backfill_index<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    key: &LegacyKey,
+) -> StdResult<()> {
+    let owner = match &key.owner {
+        Some(owner) => deps.api.canonical_address(owner)?,
+        None => return Ok(()),
+    };
+    let cid = Cid::from_str(&key.cid).map_err(|e| StdError::parse_err("Cid", e))?;
+    if may_load_block_data(&deps.storage, &cid).is_none()
+        || may_load_block_index(&deps.storage, &cid)?.is_some()
+    {
+        return Ok(());
+    }
+
+    let index = BlockIndex {
+        path: key.path.clone(),
+        owner,
+        signer: None,
+    };
+    save_block_index(&mut deps.storage, &cid, &index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordered_migrations() {
+        assert_eq!(
+            pending(UNVERSIONED, "0.2.0").unwrap(),
+            vec![
+                Migration::UpgradeState,
+                Migration::RekeyStorage,
+                Migration::BackfillIndexes
+            ]
+        );
+        assert_eq!(pending("0.2.0", "0.2.0").unwrap(), vec![]);
+        assert_eq!(pending("0.1.0", "0.1.5").unwrap(), vec![]);
+        pending("0.3.0", "0.2.0").unwrap_err();
+        assert!(parse_version("0.10.0").unwrap() > parse_version("0.9.1").unwrap());
+        parse_version("1.0").unwrap_err();
+    }
+}
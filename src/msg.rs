@@ -1,6 +1,14 @@
 use crate::state::File;
 use crate::state::Metadata;
 use crate::state::MetadataSchema;
+use crate::did::DidDocument;
+use crate::license::{LicenseTerm, Royalty};
+use crate::nft::NftExtension;
+use crate::state::{
+    Attestation, BlockTime, CredentialRecord, Expiration, FeeRate, LinkPolicy, Provenance, Quotas, RelayerKey,
+    Signature, SignatureAlgo, StatusPurpose, SwapState, ValidationRules,
+};
+use cosmwasm_std::{Binary, Coin, HumanAddr};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -12,6 +20,13 @@ pub struct InitMsg {
     // pub metadata: Metadata,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    /// Keys written by versions before 0.2.0, rewritten by the storage migration
+    #[serde(default)]
+    pub legacy_keys: Vec<LegacyKey>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
@@ -22,19 +37,370 @@ pub enum HandleMsg {
         mode: String,
         time: u64,
         content_type: String,
+        /// Redacts `path` and `owner` from the logs of the write
+        #[serde(default)]
+        private: bool,
+        #[serde(default)]
+        encoding: FileEncoding,
     },
     AddMetadata {
         path: String,
         data: MetadataSchema,
+        #[serde(default)]
+        signature: Option<Signature>,
+        #[serde(default)]
+        issuer_key: Option<Binary>,
+        /// Redacts `path`, `owner` and `parent` from the logs of the write
+        #[serde(default)]
+        private: bool,
+        /// Checked in addition to the contract's `link_policy`
+        #[serde(default)]
+        link_policy: Option<LinkPolicy>,
+    },
+    Attest {
+        cid: String,
+        pubkey: Binary,
+        algo: SignatureAlgo,
+        signature: Binary,
+        claim: String,
+    },
+    RegisterDid {
+        did: String,
+        controllers: Option<Vec<HumanAddr>>,
+    },
+    AddVerificationMethod {
+        did: String,
+        id: String,
+        algo: SignatureAlgo,
+        public_key: Binary,
+    },
+    RemoveVerificationMethod {
+        did: String,
+        id: String,
+    },
+    AddService {
+        did: String,
+        id: String,
+        service_type: String,
+        endpoint: String,
+    },
+    RemoveService {
+        did: String,
+        id: String,
+    },
+    SetControllers {
+        did: String,
+        controllers: Vec<HumanAddr>,
+    },
+    SetRevoked {
+        issuer: String,
+        list_id: String,
+        index: u32,
+        revoked: bool,
+        /// Purpose of a new list; an existing list keeps its own
+        #[serde(default)]
+        purpose: StatusPurpose,
+    },
+    LockForSwap {
+        cid: String,
+        target_chain: String,
+        recipient: String,
+        hashlock: Binary,
+        timeout_height: u64,
+    },
+    ClaimSwap {
+        preimage: Binary,
+    },
+    RefundSwap {
+        swap_id: String,
+    },
+    SetRelayers {
+        keys: Vec<RelayerKey>,
+        threshold: u32,
+    },
+    MintFromRemote {
+        source_chain: String,
+        source_cid: String,
+        proof: MintProof,
+        relayer_sig: Vec<Signature>,
+    },
+    MintNft {
+        token_id: String,
+        metadata_cid: String,
+        owner: Option<HumanAddr>,
+        /// Signature of the mint by a key that signed the metadata
+        #[serde(default)]
+        signature: Option<Signature>,
+    },
+    TransferNft {
+        recipient: HumanAddr,
+        token_id: String,
+    },
+    Approve {
+        spender: HumanAddr,
+        token_id: String,
+        expires: Option<Expiration>,
+    },
+    Revoke {
+        spender: HumanAddr,
+        token_id: String,
+    },
+    ApproveAll {
+        operator: HumanAddr,
+        expires: Option<Expiration>,
+    },
+    RevokeAll {
+        operator: HumanAddr,
+    },
+    SetViewingKey {
+        key: String,
+    },
+    SetFees {
+        fees: Vec<FeeRate>,
+    },
+    WithdrawFees {
+        recipient: HumanAddr,
+        amount: Option<Vec<Coin>>,
+    },
+    SetQuotas {
+        max_bytes: Option<u64>,
+        max_writes_per_block: Option<u32>,
+    },
+    SetValidationRules {
+        rules: ValidationRules,
+    },
+    MigrateStorage {
+        keys: Vec<LegacyKey>,
+    },
+    Batch {
+        ops: Vec<Op>,
+    },
+    ImportCar {
+        car: Binary,
+    },
+    PutBlock {
+        cid: String,
+        data: Binary,
+    },
+    /// Any document as DAG-JSON, stored as canonical DAG-CBOR
+    AddDag {
+        path: String,
+        dag_json: String,
+        #[serde(default)]
+        private: bool,
+        #[serde(default)]
+        link_policy: Option<LinkPolicy>,
+    },
+    /// An IPLD Schema in its DSL, or a JSON Schema object, that documents can declare as their `schema`
+    RegisterSchema {
+        name: String,
+        ipld_schema_dsl: String,
     },
 }
 
+/// One write of a `Batch`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Op {
+    AddFile {
+        path: String,
+        content: Vec<u8>,
+        mode: String,
+        time: u64,
+        content_type: String,
+        #[serde(default)]
+        private: bool,
+        #[serde(default)]
+        encoding: FileEncoding,
+    },
+    AddMetadata {
+        path: String,
+        data: MetadataSchema,
+        #[serde(default)]
+        signature: Option<Signature>,
+        #[serde(default)]
+        issuer_key: Option<Binary>,
+        #[serde(default)]
+        private: bool,
+        #[serde(default)]
+        link_policy: Option<LinkPolicy>,
+    },
+    /// A raw, DAG-PB, DAG-CBOR or DAG-JSON block, stored as is if `data` hashes to `cid`
+    PutBlock { cid: String, data: Binary },
+}
+
+/// How `AddFile` encodes the file
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FileEncoding {
+    /// One DAG-CBOR map holding the content with its path, type, time and mode
+    DagCbor,
+    /// The content alone as UnixFS v1 over DAG-PB, with the CIDv0 `ipfs add` gives it
+    Unixfs,
+}
+
+impl Default for FileEncoding {
+    fn default() -> Self {
+        FileEncoding::DagCbor
+    }
+}
+
+/// How `GetFile` and `GetMetadata` return the stored block
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum DocumentFormat {
+    Raw,
+    DagJson,
+    Typed,
+}
+
+impl Default for DocumentFormat {
+    fn default() -> Self {
+        DocumentFormat::Raw
+    }
+}
+
+/// A stored block in the requested `DocumentFormat`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Document<T> {
+    /// The encoded bytes
+    Raw(Binary),
+    /// The IPLD DAG-JSON encoding
+    DagJson(String),
+    /// Decoded, with links as CID strings
+    Typed(T),
+}
+
+/// A block written under the old `"<cid>::<path>"` string key
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyKey {
+    pub cid: String,
+    pub path: String,
+    /// Whether the key was written by `AddFile` or `AddMetadata`
+    pub kind: LegacyKind,
+    /// Sender of the write, used to index blocks stored before the index existed
+    #[serde(default)]
+    pub owner: Option<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LegacyKind {
+    File,
+    Metadata,
+}
+
+/// What a relayer observed on the source chain for a cross-chain mint
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MintProof {
+    /// DAG-CBOR bytes of the relayed block
+    pub data: Binary,
+    pub tx_hash: String,
+    pub nonce: u64,
+    pub recipient: HumanAddr,
+}
+
+/// A registered schema, in registration order
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SchemaInfo {
+    pub name: String,
+    pub cid: String,
+    pub owner: HumanAddr,
+}
+
+/// Address and viewing key used to authenticate SNIP-721 queries
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ViewerInfo {
+    pub address: HumanAddr,
+    pub viewing_key: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw721Approval {
+    pub spender: HumanAddr,
+    pub expires: Expiration,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     // add QueryMsg types here
-    GetFile { cid: String, path: String},
-    GetMetadata { cid: String, path: String},
+    GetFile {
+        cid: String,
+        path: String,
+        #[serde(default)]
+        format: DocumentFormat,
+    },
+    GetMetadata {
+        cid: String,
+        path: String,
+        #[serde(default)]
+        format: DocumentFormat,
+    },
+    GetSignatures { cid: String },
+    /// Attestations after position `start_after`, at most `limit` (10 by default, 30 at most)
+    ListAttestations {
+        cid: String,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    },
+    GetCredential { cid: String },
+    IsSignedBy {
+        cid: String,
+        required: Vec<Binary>,
+        threshold: u32,
+        /// Only count attestations of this claim
+        #[serde(default)]
+        claim: Option<String>,
+    },
+    ResolveDid { did: String },
+    CredentialStatus {
+        issuer: String,
+        list_id: String,
+        index: u32,
+    },
+    StatusList { issuer: String, list_id: String },
+    SwapStatus { swap_id: String },
+    GetProvenance { cid: String },
+    OwnerOf {
+        token_id: String,
+        viewer: Option<ViewerInfo>,
+        include_expired: Option<bool>,
+        /// Current height and time; without it no approval counts as expired
+        #[serde(default)]
+        block: Option<BlockTime>,
+    },
+    NftInfo { token_id: String },
+    LicenseTerms { cid: String },
+    EstimateFee {
+        bytes: u64,
+        /// Writes in the message, as in a `Batch`; defaults to 1
+        #[serde(default)]
+        ops: Option<u64>,
+    },
+    Usage { address: HumanAddr },
+    ValidationRules {},
+    ContractVersion {},
+    GetBlock { cid: String },
+    ExportCar {
+        root: String,
+        max_depth: Option<u32>,
+        max_bytes: Option<u64>,
+        cursor: Option<Binary>,
+    },
+    /// Schemas after position `start_after`, at most `limit` (10 by default, 30 at most)
+    ListSchemas {
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    },
+    PrivateMetadata {
+        token_id: String,
+        viewer: ViewerInfo,
+        /// Current height and time; without it only the owner is allowed
+        #[serde(default)]
+        block: Option<BlockTime>,
+    },
 }
 
 /// Responses from handle function
@@ -44,6 +410,36 @@ pub enum HandleAnswer {
     // add HandleMsg response types here
     AddFile { cid: String },
     AddMetadata { cid: String },
+    Attest { cid: String },
+    RegisterDid { did: String },
+    AddVerificationMethod { did: String },
+    RemoveVerificationMethod { did: String },
+    AddService { did: String },
+    RemoveService { did: String },
+    SetControllers { did: String },
+    SetRevoked { list_id: String },
+    LockForSwap { swap_id: String },
+    ClaimSwap { swap_id: String },
+    RefundSwap { swap_id: String },
+    SetRelayers { threshold: u32 },
+    MintFromRemote { cid: String },
+    MintNft { token_id: String },
+    TransferNft { token_id: String },
+    Approve { token_id: String },
+    Revoke { token_id: String },
+    ApproveAll { operator: HumanAddr },
+    RevokeAll { operator: HumanAddr },
+    SetViewingKey { address: HumanAddr },
+    SetFees { fees: Vec<FeeRate> },
+    WithdrawFees { amount: Vec<Coin> },
+    SetQuotas { quotas: Quotas },
+    SetValidationRules { rules: ValidationRules },
+    MigrateStorage { migrated: u32 },
+    Batch { cids: Vec<String> },
+    ImportCar { roots: Vec<String> },
+    PutBlock { cid: String },
+    AddDag { cid: String },
+    RegisterSchema { cid: String },
 }
 
 /// Responses from query function
@@ -51,9 +447,88 @@ pub enum HandleAnswer {
 #[serde(rename_all = "snake_case")]
 pub enum QueryAnswer {
     GetFile {
-        data: Vec<u8>,
+        data: Document<File>,
     },
     GetMetadata {
-        data: Vec<u8>,
+        data: Document<Metadata>,
+    },
+    GetSignatures {
+        signatures: Vec<Signature>,
+    },
+    ListAttestations {
+        attestations: Vec<Attestation>,
+    },
+    IsSignedBy {
+        signed: bool,
+        count: u32,
+    },
+    GetCredential {
+        credential: Option<CredentialRecord>,
+    },
+    ResolveDid {
+        document: DidDocument,
+    },
+    CredentialStatus {
+        revoked: bool,
+    },
+    StatusList {
+        purpose: StatusPurpose,
+        encoded_list: String,
+    },
+    SwapStatus {
+        cid: String,
+        token_id: String,
+        sender: HumanAddr,
+        target_chain: String,
+        recipient: String,
+        hashlock: Binary,
+        timeout_height: u64,
+        state: SwapState,
+        preimage: Option<Binary>,
+    },
+    GetProvenance {
+        provenance: Option<Provenance>,
+    },
+    OwnerOf {
+        owner: HumanAddr,
+        approvals: Vec<Cw721Approval>,
+    },
+    NftInfo {
+        token_uri: Option<String>,
+        extension: Option<NftExtension>,
+    },
+    PrivateMetadata {
+        token_uri: Option<String>,
+        extension: Option<NftExtension>,
+    },
+    LicenseTerms {
+        terms: Vec<LicenseTerm>,
+        royalties: Vec<Royalty>,
+    },
+    EstimateFee {
+        fees: Vec<Coin>,
+    },
+    Usage {
+        bytes_used: u64,
+        block_count: u64,
+        remaining_bytes: Option<u64>,
+    },
+    ValidationRules {
+        rules: ValidationRules,
+    },
+    ContractVersion {
+        name: String,
+        version: String,
+    },
+    GetBlock {
+        data: Binary,
+    },
+    /// A page of a CARv1 stream; `cursor` is set when more blocks follow
+    ExportCar {
+        car: Binary,
+        cursor: Option<Binary>,
+    },
+    ListSchemas {
+        schemas: Vec<SchemaInfo>,
     },
 }
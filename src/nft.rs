@@ -0,0 +1,118 @@
+use cosmwasm_std::{StdError, StdResult};
+use libipld::ipld::Ipld;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// SNIP-721 metadata, filled from a stored metadata block
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftMetadata {
+    pub token_uri: Option<String>,
+    pub extension: Option<NftExtension>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftExtension {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub attributes: Option<Vec<NftTrait>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftTrait {
+    pub trait_type: Option<String>,
+    pub value: String,
+}
+
+fn field<'a>(node: &'a Ipld, key: &str) -> Option<&'a Ipld> {
+    match node {
+        Ipld::Map(map) => map.get(key),
+        _ => None,
+    }
+}
+
+fn string_field(node: &Ipld, key: &str) -> Option<String> {
+    match field(node, key) {
+        Some(Ipld::String(value)) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn link_field(value: &Ipld) -> Option<String> {
+    match value {
+        Ipld::Link(cid) => Some(cid.to_string()),
+        Ipld::String(value) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// `owner` of a metadata document
+pub fn metadata_owner(node: &Ipld) -> Option<String> {
+    string_field(node, "owner")
+}
+
+/// Fails unless the decoded block looks like a `MetadataSchema` document
+pub fn check_metadata(node: &Ipld) -> StdResult<()> {
+    match (
+        string_field(node, "name"),
+        string_field(node, "description"),
+    ) {
+        (Some(_), Some(_)) => Ok(()),
+        _ => Err(StdError::generic_err(
+            "Tokens can only be minted for metadata blocks",
+        )),
+    }
+}
+
+/// What anyone can see: name, description and image
+pub fn public_metadata(node: &Ipld) -> NftMetadata {
+    NftMetadata {
+        token_uri: None,
+        extension: Some(NftExtension {
+            name: string_field(node, "name"),
+            description: string_field(node, "description"),
+            image: string_field(node, "image"),
+            attributes: None,
+        }),
+    }
+}
+
+/// What owners and approved addresses see: the CID plus lineage, sources, refs and owner as attributes
+pub fn private_metadata(cid: &str, node: &Ipld) -> NftMetadata {
+    let mut attributes = vec![];
+    let mut push = |trait_type: &str, value: Option<String>| {
+        if let Some(value) = value {
+            attributes.push(NftTrait {
+                trait_type: Some(trait_type.to_string()),
+                value,
+            });
+        }
+    };
+
+    push("parent", field(node, "parent").and_then(link_field));
+    for key in &["sources", "refs"] {
+        if let Some(Ipld::List(items)) = field(node, key) {
+            for item in items {
+                push(&key[..key.len() - 1], link_field(item));
+            }
+        }
+    }
+    push("owner", string_field(node, "owner"));
+
+    let mut metadata = public_metadata(node);
+    metadata.token_uri = Some(format!("ipfs://{}", cid));
+    if let Some(extension) = &mut metadata.extension {
+        extension.attributes = Some(attributes);
+    }
+    metadata
+}
+
+pub fn hash_viewing_key(key: &str) -> Vec<u8> {
+    Sha256::digest(key.as_bytes()).to_vec()
+}
+
+pub fn check_viewing_key(hash: &[u8], key: &str) -> bool {
+    bool::from(hash.ct_eq(&hash_viewing_key(key)))
+}
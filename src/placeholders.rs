@@ -0,0 +1,72 @@
+use cosmwasm_std::{StdError, StdResult};
+use libipld::Cid;
+
+use crate::state::MetadataSchema;
+
+pub const PLACEHOLDER_PREFIX: &str = "$op:";
+
+/// Replaces `"$op:N"` in `refs`, `sources`, `parent`, `image` and license `source`s with the CID written by
+/// operation `N` of the batch. `written` holds the CIDs of the operations before this one.
+pub fn resolve_placeholders(data: &mut MetadataSchema, written: &[Cid]) -> StdResult<()> {
+    resolve(&mut data.parent, written)?;
+    resolve(&mut data.image, written)?;
+    for link in data.refs.iter_mut().chain(data.sources.iter_mut()) {
+        resolve(link, written)?;
+    }
+    if let Some(licenses) = &mut data.licenses {
+        for license in licenses {
+            resolve(&mut license.source, written)?;
+        }
+    }
+    Ok(())
+}
+
+fn resolve(value: &mut String, written: &[Cid]) -> StdResult<()> {
+    let index = match value.strip_prefix(PLACEHOLDER_PREFIX) {
+        Some(index) => index,
+        None => return Ok(()),
+    };
+    let cid = index
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| written.get(i))
+        .ok_or_else(|| {
+            StdError::generic_err(format!("{} does not name an earlier operation", value))
+        })?;
+    *value = cid.to_string();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn placeholders() {
+        let file = Cid::from_str("QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D").unwrap();
+        let mut data = MetadataSchema {
+            name: "doc".to_string(),
+            description: "".to_string(),
+            image: "$op:0".to_string(),
+            sources: vec!["$op:0".to_string()],
+            parent: "QmdmQXB2mzChmMeKY47C43LxUdg1NDJ5MWcKMKxDu7RgQm".to_string(),
+            refs: vec!["$op:0".to_string()],
+            owner: None,
+            verified_credential: None,
+            licenses: None,
+            schema: None,
+        };
+
+        resolve_placeholders(&mut data, &[file]).unwrap();
+        assert_eq!(data.image, file.to_string());
+        assert_eq!(data.sources, vec![file.to_string()]);
+        assert_eq!(data.refs, vec![file.to_string()]);
+        assert_eq!(data.parent, "QmdmQXB2mzChmMeKY47C43LxUdg1NDJ5MWcKMKxDu7RgQm");
+
+        data.parent = "$op:1".to_string();
+        resolve_placeholders(&mut data, &[file]).unwrap_err();
+        data.parent = "$op:x".to_string();
+        resolve_placeholders(&mut data, &[file]).unwrap_err();
+    }
+}
@@ -0,0 +1,137 @@
+use cosmwasm_std::{to_vec, StdError};
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+
+use crate::state::{Quotas, Usage};
+
+/// Sent to clients as the JSON encoding of the error, such as
+/// `{"quota_exceeded":{"used":80,"requested":30,"quota":100}}`
+#[derive(Snafu, Serialize, Deserialize, Debug, PartialEq)]
+#[snafu(visibility = "pub")]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaError {
+    #[snafu(display(
+        "Storage quota exceeded: {} of {} bytes used, write needs {}",
+        used,
+        quota,
+        requested
+    ))]
+    QuotaExceeded {
+        used: u64,
+        requested: u64,
+        quota: u64,
+    },
+    #[snafu(display("Write limit of {} per block reached", limit))]
+    RateLimited { limit: u32 },
+}
+
+impl From<QuotaError> for StdError {
+    fn from(err: QuotaError) -> Self {
+        match to_vec(&err) {
+            Ok(json) => StdError::generic_err(String::from_utf8_lossy(&json)),
+            Err(e) => e,
+        }
+    }
+}
+
+/// Books `writes` writes of `bytes` spread over `blocks` blocks at `height`, unless they break a limit
+pub fn record_write(
+    quotas: &Quotas,
+    usage: &mut Usage,
+    height: u64,
+    writes: u32,
+    bytes: u64,
+    blocks: u64,
+) -> Result<(), QuotaError> {
+    let writes_at_height = if usage.last_height == height {
+        usage.writes_at_height
+    } else {
+        0
+    };
+    if let Some(limit) = quotas.max_writes_per_block {
+        if writes_at_height.saturating_add(writes) > limit {
+            return Err(QuotaError::RateLimited { limit });
+        }
+    }
+    if let Some(quota) = quotas.max_bytes {
+        if usage.bytes.saturating_add(bytes) > quota {
+            return Err(QuotaError::QuotaExceeded {
+                used: usage.bytes,
+                requested: bytes,
+                quota,
+            });
+        }
+    }
+
+    usage.bytes = usage.bytes.saturating_add(bytes);
+    usage.blocks += blocks;
+    usage.last_height = height;
+    usage.writes_at_height = writes_at_height.saturating_add(writes);
+    Ok(())
+}
+
+/// Bytes left before the quota is reached, `None` when there is no quota
+pub fn remaining_bytes(quotas: &Quotas, usage: &Usage) -> Option<u64> {
+    quotas
+        .max_bytes
+        .map(|quota| quota.saturating_sub(usage.bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits() {
+        let quotas = Quotas {
+            max_bytes: Some(100),
+            max_writes_per_block: Some(2),
+        };
+        let mut usage = Usage::default();
+
+        record_write(&quotas, &mut usage, 1, 1, 40, 1).unwrap();
+        record_write(&quotas, &mut usage, 1, 1, 40, 2).unwrap();
+        assert_eq!(
+            record_write(&quotas, &mut usage, 1, 1, 10, 1),
+            Err(QuotaError::RateLimited { limit: 2 })
+        );
+        // a batch counts each of its writes
+        assert_eq!(
+            record_write(&quotas, &mut usage, 2, 3, 10, 3),
+            Err(QuotaError::RateLimited { limit: 2 })
+        );
+        assert_eq!(
+            record_write(&quotas, &mut usage, 2, 1, 30, 1),
+            Err(QuotaError::QuotaExceeded {
+                used: 80,
+                requested: 30,
+                quota: 100
+            })
+        );
+        record_write(&quotas, &mut usage, 2, 1, 20, 1).unwrap();
+
+        assert_eq!(usage.bytes, 100);
+        assert_eq!(usage.blocks, 4);
+        assert_eq!(remaining_bytes(&quotas, &usage), Some(0));
+        assert_eq!(remaining_bytes(&Quotas::default(), &usage), None);
+    }
+
+    #[test]
+    fn errors_are_json() {
+        let err: StdError = QuotaError::QuotaExceeded {
+            used: 80,
+            requested: 30,
+            quota: 100,
+        }
+        .into();
+        assert_eq!(
+            err,
+            StdError::generic_err(r#"{"quota_exceeded":{"used":80,"requested":30,"quota":100}}"#)
+        );
+        let err: StdError = QuotaError::RateLimited { limit: 2 }.into();
+        assert_eq!(
+            err,
+            StdError::generic_err(r#"{"rate_limited":{"limit":2}}"#)
+        );
+    }
+}
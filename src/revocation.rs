@@ -0,0 +1,101 @@
+use std::io::Write;
+
+use cosmwasm_std::{StdError, StdResult};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// Bits in a status list; 16KB is the StatusList2021 minimum, which keeps holders private
+pub const STATUS_LIST_BITS: u32 = 131_072;
+
+pub fn check_index(index: u32) -> StdResult<()> {
+    if index >= STATUS_LIST_BITS {
+        return Err(StdError::generic_err(format!(
+            "Status list index must be below {}",
+            STATUS_LIST_BITS
+        )));
+    }
+    Ok(())
+}
+
+/// Reads bit `index`, counting from the most significant bit of the first byte
+pub fn is_set(bits: &[u8], index: u32) -> bool {
+    match bits.get((index / 8) as usize) {
+        Some(byte) => byte & (0x80 >> (index % 8)) != 0,
+        None => false,
+    }
+}
+
+/// Sets or clears bit `index`; the list is only grown as far as needed
+pub fn set_bit(bits: &mut Vec<u8>, index: u32, value: bool) {
+    let pos = (index / 8) as usize;
+    if bits.len() <= pos {
+        if !value {
+            return;
+        }
+        bits.resize(pos + 1, 0);
+    }
+    let mask = 0x80 >> (index % 8);
+    if value {
+        bits[pos] |= mask;
+    } else {
+        bits[pos] &= !mask;
+    }
+}
+
+/// `encodedList` of a StatusList2021 credential: the full size bitstring, GZIP compressed, base64url
+pub fn encode_list(bits: &[u8]) -> StdResult<String> {
+    let mut full = bits.to_vec();
+    full.resize((STATUS_LIST_BITS / 8) as usize, 0);
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder
+        .write_all(&full)
+        .map_err(|e| StdError::serialize_err("StatusList", e))?;
+    let compressed = encoder
+        .finish()
+        .map_err(|e| StdError::serialize_err("StatusList", e))?;
+
+    Ok(base64::encode_config(&compressed, base64::URL_SAFE_NO_PAD))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn set_and_clear() {
+        let mut bits = vec![];
+        set_bit(&mut bits, 10, false);
+        assert!(bits.is_empty());
+
+        set_bit(&mut bits, 0, true);
+        set_bit(&mut bits, 10, true);
+        assert_eq!(bits, vec![0x80, 0x20]);
+        assert!(is_set(&bits, 10));
+        assert!(!is_set(&bits, 11));
+        assert!(!is_set(&bits, 5000));
+
+        set_bit(&mut bits, 10, false);
+        assert!(!is_set(&bits, 10));
+        check_index(STATUS_LIST_BITS).unwrap_err();
+    }
+
+    #[test]
+    fn encoded_list_roundtrip() {
+        let mut bits = vec![];
+        set_bit(&mut bits, 3, true);
+
+        let encoded = encode_list(&bits).unwrap();
+        let compressed = base64::decode_config(&encoded, base64::URL_SAFE_NO_PAD).unwrap();
+        let mut decoded = vec![];
+        GzDecoder::new(&compressed[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+
+        assert_eq!(decoded.len(), (STATUS_LIST_BITS / 8) as usize);
+        assert_eq!(decoded[0], 0x10);
+        assert!(decoded[1..].iter().all(|b| *b == 0));
+    }
+}
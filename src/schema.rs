@@ -0,0 +1,730 @@
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use cosmwasm_std::{StdError, StdResult};
+use libipld::{codec::Codec, ipld, ipld::Ipld, json::DagJsonCodec};
+
+/// Types every IPLD Schema can name without declaring them
+const PRELUDE: &[&str] = &[
+    "Bool", "String", "Bytes", "Int", "Float", "Map", "List", "Link", "Any",
+];
+/// Scalar kinds a `type Name <kind>` declaration can use
+const KINDS: &[&str] = &["bool", "string", "bytes", "int", "float", "any"];
+/// Deepest document nesting that gets validated
+const MAX_DEPTH: usize = 32;
+/// JSON Schema keywords `check_json` validates with
+const JSON_KEYWORDS: &[&str] = &[
+    "type",
+    "enum",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+];
+/// JSON Schema annotations, accepted but not validated with
+const JSON_ANNOTATIONS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$comment",
+    "title",
+    "description",
+    "default",
+    "examples",
+];
+/// Names the `type` keyword accepts
+const JSON_TYPES: &[&str] = &[
+    "object", "array", "string", "integer", "number", "boolean", "null",
+];
+
+/// Compiles a schema into the record stored by `RegisterSchema`. Text starting with `{` is read as a JSON
+/// Schema and kept as is under `jsonSchema`; anything else is IPLD Schema DSL, stored as its data model
+/// form under `schema` with the first declared type as `root`.
+pub fn compile(name: &str, text: &str) -> StdResult<Ipld> {
+    if name.is_empty() {
+        return Err(StdError::generic_err("Schemas need a name"));
+    }
+    if text.trim_start().starts_with('{') {
+        let json_schema = DagJsonCodec
+            .decode::<Ipld>(text.as_bytes())
+            .map_err(|e| StdError::parse_err("JSON Schema", e))?;
+        check_json_schema(&json_schema, "", 0)?;
+        return Ok(ipld!({ "name": name, "jsonSchema": json_schema }));
+    }
+
+    let (root, types) = Parser::new(text)?.parse()?;
+    Ok(ipld!({
+        "name": name,
+        "root": root,
+        "schema": ipld!({ "types": Ipld::Map(types) }),
+    }))
+}
+
+/// Checks a document against a compiled schema. A top level `schema` key names the schema itself and is
+/// not validated.
+pub fn validate(record: &Ipld, document: &Ipld) -> StdResult<()> {
+    let mut document = document.clone();
+    if let Ipld::Map(map) = &mut document {
+        map.remove("schema");
+    }
+
+    if let Some(json_schema) = get(record, "jsonSchema") {
+        return check_json(json_schema, &document, "", 0);
+    }
+    let types = match get(record, "schema").and_then(|schema| get(schema, "types")) {
+        Some(Ipld::Map(types)) => types,
+        _ => return Err(StdError::generic_err("Invalid schema record")),
+    };
+    let root = match get(record, "root") {
+        Some(Ipld::String(root)) => root,
+        _ => return Err(StdError::generic_err("Invalid schema record")),
+    };
+    Validator { types }.check_type(&Ipld::String(root.clone()), &document, "", 0)
+}
+
+fn get<'a>(node: &'a Ipld, key: &str) -> Option<&'a Ipld> {
+    match node {
+        Ipld::Map(map) => map.get(key),
+        _ => None,
+    }
+}
+
+fn mismatch(at: &str, expected: &str) -> StdError {
+    let at = if at.is_empty() { "/" } else { at };
+    StdError::generic_err(format!(
+        "Document does not match its schema at {}: expected {}",
+        at, expected
+    ))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Punct(char),
+}
+
+/// Reads the subset of the IPLD Schema DSL with map represented structs, string represented enums,
+/// scalar kinds, lists, maps and links
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    referenced: Vec<String>,
+}
+
+impl Parser {
+    fn new(dsl: &str) -> StdResult<Self> {
+        let mut tokens = vec![];
+        let mut chars = dsl.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '#' => {
+                    while let Some(c) = chars.peek() {
+                        if *c == '\n' {
+                            break;
+                        }
+                        chars.next();
+                    }
+                }
+                '{' | '}' | '[' | ']' | ':' | '&' | '|' => tokens.push(Token::Punct(c)),
+                c if c.is_whitespace() => {}
+                c if c.is_ascii_alphanumeric() || c == '_' => {
+                    tokens.push(Token::Word(read_word(c, &mut chars)))
+                }
+                c => {
+                    return Err(StdError::generic_err(format!(
+                        "Unexpected {:?} in schema",
+                        c
+                    )))
+                }
+            }
+        }
+        Ok(Parser {
+            tokens,
+            pos: 0,
+            referenced: vec![],
+        })
+    }
+
+    fn parse(mut self) -> StdResult<(String, BTreeMap<String, Ipld>)> {
+        let mut root = None;
+        let mut types = BTreeMap::new();
+        while self.peek().is_some() {
+            self.expect_word("type")?;
+            let name = self.word()?;
+            if PRELUDE.contains(&name.as_str()) || types.contains_key(&name) {
+                return Err(StdError::generic_err(format!(
+                    "Type {} is declared twice",
+                    name
+                )));
+            }
+            let definition = self.type_definition()?;
+            root.get_or_insert_with(|| name.clone());
+            types.insert(name, definition);
+        }
+
+        for name in &self.referenced {
+            if !PRELUDE.contains(&name.as_str()) && !types.contains_key(name) {
+                return Err(StdError::generic_err(format!(
+                    "Type {} is not declared",
+                    name
+                )));
+            }
+        }
+        let root = root.ok_or_else(|| StdError::generic_err("The schema declares no types"))?;
+        Ok((root, types))
+    }
+
+    fn type_definition(&mut self) -> StdResult<Ipld> {
+        match self.peek() {
+            Some(Token::Word(word)) if word == "struct" => {
+                self.pos += 1;
+                self.struct_definition()
+            }
+            Some(Token::Word(word)) if word == "enum" => {
+                self.pos += 1;
+                self.enum_definition()
+            }
+            Some(Token::Word(word)) if KINDS.contains(&word.as_str()) => {
+                let kind = self.word()?;
+                Ok(Ipld::Map(vec![(kind, ipld!({}))].into_iter().collect()))
+            }
+            Some(Token::Punct(_)) => match self.type_expression()? {
+                Ipld::String(name) => Err(StdError::generic_err(format!(
+                    "Unexpected type name {}",
+                    name
+                ))),
+                definition => Ok(definition),
+            },
+            Some(Token::Word(word)) => Err(StdError::generic_err(format!(
+                "Unsupported type kind {}",
+                word
+            ))),
+            None => Err(StdError::generic_err("Unexpected end of schema")),
+        }
+    }
+
+    fn struct_definition(&mut self) -> StdResult<Ipld> {
+        self.expect_punct('{')?;
+        let mut fields = BTreeMap::new();
+        while self.peek() != Some(&Token::Punct('}')) {
+            let name = self.word()?;
+            let optional = self.modifier("optional");
+            let nullable = self.modifier("nullable");
+            let field_type = self.type_expression()?;
+            fields.insert(
+                name,
+                ipld!({ "type": field_type, "optional": optional, "nullable": nullable }),
+            );
+        }
+        self.expect_punct('}')?;
+        self.representation("map")?;
+        Ok(ipld!({
+            "struct": ipld!({
+                "fields": Ipld::Map(fields),
+                "representation": ipld!({ "map": ipld!({}) }),
+            }),
+        }))
+    }
+
+    fn enum_definition(&mut self) -> StdResult<Ipld> {
+        self.expect_punct('{')?;
+        let mut members = vec![];
+        while self.peek() != Some(&Token::Punct('}')) {
+            self.expect_punct('|')?;
+            members.push(Ipld::String(self.word()?));
+        }
+        self.expect_punct('}')?;
+        self.representation("string")?;
+        Ok(ipld!({
+            "enum": ipld!({
+                "members": members,
+                "representation": ipld!({ "string": ipld!({}) }),
+            }),
+        }))
+    }
+
+    /// A type name, or an inline list, map or link
+    fn type_expression(&mut self) -> StdResult<Ipld> {
+        match self.next() {
+            Some(Token::Punct('[')) => {
+                let nullable = self.modifier("nullable");
+                let value_type = self.type_expression()?;
+                self.expect_punct(']')?;
+                Ok(ipld!({
+                    "list": ipld!({ "valueType": value_type, "valueNullable": nullable }),
+                }))
+            }
+            Some(Token::Punct('{')) => {
+                let key_type = self.word()?;
+                self.expect_punct(':')?;
+                let nullable = self.modifier("nullable");
+                let value_type = self.type_expression()?;
+                self.expect_punct('}')?;
+                Ok(ipld!({
+                    "map": ipld!({
+                        "keyType": key_type,
+                        "valueType": value_type,
+                        "valueNullable": nullable,
+                    }),
+                }))
+            }
+            Some(Token::Punct('&')) => {
+                let expected = self.word()?;
+                self.referenced.push(expected.clone());
+                Ok(ipld!({ "link": ipld!({ "expectedType": expected }) }))
+            }
+            Some(Token::Word(name)) => {
+                self.referenced.push(name.clone());
+                Ok(Ipld::String(name))
+            }
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Only the default representation of each kind is supported
+    fn representation(&mut self, supported: &str) -> StdResult<()> {
+        if self.modifier("representation") {
+            let representation = self.word()?;
+            if representation != supported {
+                return Err(StdError::generic_err(format!(
+                    "Unsupported representation {}",
+                    representation
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn modifier(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if word == keyword => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn word(&mut self) -> StdResult<String> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    fn expect_word(&mut self, expected: &str) -> StdResult<()> {
+        match self.next() {
+            Some(Token::Word(word)) if word == expected => Ok(()),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    fn expect_punct(&mut self, expected: char) -> StdResult<()> {
+        match self.next() {
+            Some(Token::Punct(c)) if c == expected => Ok(()),
+            other => Err(unexpected(other)),
+        }
+    }
+}
+
+fn read_word(first: char, chars: &mut Peekable<Chars>) -> String {
+    let mut word = first.to_string();
+    while let Some(c) = chars.peek() {
+        if !c.is_ascii_alphanumeric() && *c != '_' {
+            break;
+        }
+        word.push(*c);
+        chars.next();
+    }
+    word
+}
+
+fn unexpected(token: Option<Token>) -> StdError {
+    match token {
+        Some(Token::Word(word)) => StdError::generic_err(format!("Unexpected {} in schema", word)),
+        Some(Token::Punct(c)) => StdError::generic_err(format!("Unexpected {} in schema", c)),
+        None => StdError::generic_err("Unexpected end of schema"),
+    }
+}
+
+/// Walks a document along the data model form of an IPLD Schema
+struct Validator<'a> {
+    types: &'a BTreeMap<String, Ipld>,
+}
+
+impl<'a> Validator<'a> {
+    fn check_type(&self, expr: &Ipld, value: &Ipld, at: &str, depth: usize) -> StdResult<()> {
+        if depth > MAX_DEPTH {
+            return Err(StdError::generic_err("Document is nested too deeply"));
+        }
+        let name = match expr {
+            Ipld::String(name) => name,
+            definition => return self.check_definition(definition, value, at, depth),
+        };
+        let matches = match name.as_str() {
+            "Bool" => matches!(value, Ipld::Bool(_)),
+            "String" => matches!(value, Ipld::String(_)),
+            "Bytes" => matches!(value, Ipld::Bytes(_)),
+            "Int" => matches!(value, Ipld::Integer(_)),
+            "Float" => matches!(value, Ipld::Float(_) | Ipld::Integer(_)),
+            "Map" => matches!(value, Ipld::Map(_)),
+            "List" => matches!(value, Ipld::List(_)),
+            "Link" => matches!(value, Ipld::Link(_)),
+            "Any" => true,
+            _ => match self.types.get(name) {
+                Some(definition) => return self.check_definition(definition, value, at, depth),
+                None => {
+                    return Err(StdError::generic_err(format!(
+                        "Type {} is not declared",
+                        name
+                    )))
+                }
+            },
+        };
+        if matches {
+            Ok(())
+        } else {
+            Err(mismatch(at, name))
+        }
+    }
+
+    fn check_definition(
+        &self,
+        definition: &Ipld,
+        value: &Ipld,
+        at: &str,
+        depth: usize,
+    ) -> StdResult<()> {
+        let (kind, body) = match definition {
+            Ipld::Map(map) if map.len() == 1 => map.iter().next().unwrap(),
+            _ => return Err(StdError::generic_err("Invalid schema type")),
+        };
+        match (kind.as_str(), value) {
+            ("struct", Ipld::Map(fields)) => {
+                let declared = match get(body, "fields") {
+                    Some(Ipld::Map(declared)) => declared,
+                    _ => return Err(StdError::generic_err("Invalid schema type")),
+                };
+                for key in fields.keys() {
+                    if !declared.contains_key(key) {
+                        return Err(mismatch(&format!("{}/{}", at, key), "no such field"));
+                    }
+                }
+                for (key, field) in declared {
+                    let at = format!("{}/{}", at, key);
+                    match fields.get(key) {
+                        None if flag(field, "optional") => {}
+                        None => return Err(mismatch(&at, "a value")),
+                        Some(Ipld::Null) if flag(field, "nullable") => {}
+                        Some(item) => {
+                            let field_type = get(field, "type").unwrap_or(&Ipld::Null);
+                            self.check_type(field_type, item, &at, depth + 1)?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            ("enum", Ipld::String(member)) => match get(body, "members") {
+                Some(Ipld::List(members)) if members.contains(&Ipld::String(member.clone())) => {
+                    Ok(())
+                }
+                _ => Err(mismatch(at, "an enum member")),
+            },
+            ("list", Ipld::List(items)) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.check_value(body, item, &format!("{}/{}", at, i), depth)?;
+                }
+                Ok(())
+            }
+            ("map", Ipld::Map(entries)) => {
+                for (key, item) in entries {
+                    self.check_value(body, item, &format!("{}/{}", at, key), depth)?;
+                }
+                Ok(())
+            }
+            ("link", Ipld::Link(_)) => Ok(()),
+            ("bool", Ipld::Bool(_))
+            | ("string", Ipld::String(_))
+            | ("bytes", Ipld::Bytes(_))
+            | ("int", Ipld::Integer(_))
+            | ("float", Ipld::Float(_))
+            | ("float", Ipld::Integer(_))
+            | ("any", _) => Ok(()),
+            (kind, _) => Err(mismatch(at, kind)),
+        }
+    }
+
+    /// A list item or map value
+    fn check_value(&self, body: &Ipld, item: &Ipld, at: &str, depth: usize) -> StdResult<()> {
+        if *item == Ipld::Null && flag(body, "valueNullable") {
+            return Ok(());
+        }
+        let value_type = get(body, "valueType").unwrap_or(&Ipld::Null);
+        self.check_type(value_type, item, at, depth + 1)
+    }
+}
+
+fn flag(node: &Ipld, key: &str) -> bool {
+    get(node, key) == Some(&Ipld::Bool(true))
+}
+
+/// Rejects JSON Schemas using keywords `check_json` would ignore, so a document is never accepted
+/// against a constraint that was not checked
+fn check_json_schema(schema: &Ipld, at: &str, depth: usize) -> StdResult<()> {
+    if depth > MAX_DEPTH {
+        return Err(StdError::generic_err("JSON Schema is nested too deeply"));
+    }
+    let shown_at = if at.is_empty() { "/" } else { at };
+    let keywords = match schema {
+        Ipld::Map(keywords) => keywords,
+        _ => return Err(StdError::generic_err("A JSON Schema must be an object")),
+    };
+    for (keyword, value) in keywords {
+        if JSON_ANNOTATIONS.contains(&keyword.as_str()) {
+            continue;
+        }
+        if !JSON_KEYWORDS.contains(&keyword.as_str()) {
+            return Err(StdError::generic_err(format!(
+                "Unsupported JSON Schema keyword {} at {}",
+                keyword, shown_at
+            )));
+        }
+        let is_type =
+            |name: &Ipld| matches!(name, Ipld::String(name) if JSON_TYPES.contains(&name.as_str()));
+        let valid = match (keyword.as_str(), value) {
+            ("type", Ipld::List(names)) => !names.is_empty() && names.iter().all(is_type),
+            ("type", name) => is_type(name),
+            ("enum", Ipld::List(_)) => true,
+            ("required", Ipld::List(keys)) => keys.iter().all(|key| matches!(key, Ipld::String(_))),
+            ("properties", Ipld::Map(properties)) => {
+                for (key, property) in properties {
+                    check_json_schema(property, &format!("{}/properties/{}", at, key), depth + 1)?;
+                }
+                true
+            }
+            ("additionalProperties", Ipld::Bool(_)) => true,
+            ("additionalProperties", additional) | ("items", additional) => {
+                check_json_schema(additional, &format!("{}/{}", at, keyword), depth + 1)?;
+                true
+            }
+            _ => false,
+        };
+        if !valid {
+            return Err(StdError::generic_err(format!(
+                "Invalid JSON Schema keyword {} at {}",
+                keyword, shown_at
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The JSON Schema keywords `type`, `enum`, `properties`, `required`, `additionalProperties` and `items`.
+/// Links validate as strings, the way the typed format renders them.
+fn check_json(schema: &Ipld, value: &Ipld, at: &str, depth: usize) -> StdResult<()> {
+    if depth > MAX_DEPTH {
+        return Err(StdError::generic_err("Document is nested too deeply"));
+    }
+    if let Some(types) = get(schema, "type") {
+        let types = match types {
+            Ipld::String(name) => vec![name.as_str()],
+            Ipld::List(names) => names
+                .iter()
+                .filter_map(|name| match name {
+                    Ipld::String(name) => Some(name.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        };
+        if !types.iter().any(|name| json_type_matches(name, value)) {
+            return Err(mismatch(at, &types.join(" or ")));
+        }
+    }
+    if let Some(Ipld::List(options)) = get(schema, "enum") {
+        let value = match value {
+            Ipld::Link(cid) => Ipld::String(cid.to_string()),
+            value => value.clone(),
+        };
+        if !options.contains(&value) {
+            return Err(mismatch(at, "one of the enum values"));
+        }
+    }
+
+    match value {
+        Ipld::Map(fields) => {
+            if let Some(Ipld::List(required)) = get(schema, "required") {
+                for key in required {
+                    if let Ipld::String(key) = key {
+                        if !fields.contains_key(key) {
+                            return Err(mismatch(&format!("{}/{}", at, key), "a value"));
+                        }
+                    }
+                }
+            }
+            for (key, item) in fields {
+                let at = format!("{}/{}", at, key);
+                let property = match get(schema, "properties") {
+                    Some(properties) => get(properties, key),
+                    None => None,
+                };
+                match (property, get(schema, "additionalProperties")) {
+                    (Some(property), _) => check_json(property, item, &at, depth + 1)?,
+                    (None, Some(Ipld::Bool(false))) => return Err(mismatch(&at, "no such field")),
+                    (None, Some(additional @ Ipld::Map(_))) => {
+                        check_json(additional, item, &at, depth + 1)?
+                    }
+                    (None, _) => {}
+                }
+            }
+        }
+        Ipld::List(items) => {
+            if let Some(item_schema) = get(schema, "items") {
+                for (i, item) in items.iter().enumerate() {
+                    check_json(item_schema, item, &format!("{}/{}", at, i), depth + 1)?;
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn json_type_matches(name: &str, value: &Ipld) -> bool {
+    matches!(
+        (name, value),
+        ("object", Ipld::Map(_))
+            | ("array", Ipld::List(_))
+            | ("string", Ipld::String(_))
+            | ("string", Ipld::Link(_))
+            | ("integer", Ipld::Integer(_))
+            | ("number", Ipld::Integer(_))
+            | ("number", Ipld::Float(_))
+            | ("boolean", Ipld::Bool(_))
+            | ("null", Ipld::Null)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libipld::Cid;
+    use std::str::FromStr;
+
+    const REVIEW: &str = r#"
+        # a review of a stored asset
+        type Review struct {
+            subject &Any
+            rating Rating
+            tags optional [String]
+            scores {String:nullable Int}
+            comment nullable String
+        }
+
+        type Rating enum {
+            | Good
+            | Bad
+        }
+    "#;
+
+    fn review() -> Ipld {
+        let subject = Cid::from_str("QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D").unwrap();
+        ipld!({
+            "subject": Ipld::Link(subject),
+            "rating": "Good",
+            "scores": ipld!({ "speed": 5, "price": Ipld::Null }),
+            "comment": Ipld::Null,
+        })
+    }
+
+    fn with(mut document: Ipld, key: &str, value: Ipld) -> Ipld {
+        if let Ipld::Map(map) = &mut document {
+            map.insert(key.to_string(), value);
+        }
+        document
+    }
+
+    #[test]
+    fn ipld_schema_dsl() {
+        let record = compile("review", REVIEW).unwrap();
+        assert_eq!(
+            get(&record, "root"),
+            Some(&Ipld::String("Review".to_string()))
+        );
+
+        validate(&record, &review()).unwrap();
+        validate(&record, &with(review(), "tags", ipld!(["fast"]))).unwrap();
+        validate(&record, &with(review(), "schema", Ipld::Null)).unwrap();
+
+        let err = validate(&record, &with(review(), "rating", ipld!("Meh"))).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(
+                "Document does not match its schema at /rating: expected an enum member"
+            )
+        );
+        validate(&record, &with(review(), "subject", ipld!("not a link"))).unwrap_err();
+        validate(&record, &with(review(), "tags", ipld!([1]))).unwrap_err();
+        validate(&record, &with(review(), "extra", ipld!(1))).unwrap_err();
+        let mut missing = review();
+        if let Ipld::Map(map) = &mut missing {
+            map.remove("rating");
+        }
+        validate(&record, &missing).unwrap_err();
+
+        compile("bad", "type A struct { b B }").unwrap_err();
+        compile("bad", "type A union { | B string }").unwrap_err();
+        compile("bad", "type A struct {} representation tuple").unwrap_err();
+        compile("bad", "").unwrap_err();
+    }
+
+    #[test]
+    fn json_schema() {
+        let record = compile(
+            "metadata",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "parent": { "type": "string" },
+                    "sources": { "type": "array", "items": { "type": "string" } }
+                },
+                "required": ["name", "parent"]
+            }"#,
+        )
+        .unwrap();
+        let parent = Cid::from_str("QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D").unwrap();
+        let document = ipld!({
+            "name": "doc",
+            "parent": Ipld::Link(parent),
+            "sources": Ipld::List(vec![Ipld::Link(parent)]),
+            "extra": true,
+        });
+        validate(&record, &document).unwrap();
+        validate(&record, &with(document.clone(), "name", ipld!(1))).unwrap_err();
+        validate(&record, &ipld!({ "name": "doc" })).unwrap_err();
+
+        compile(
+            "annotated",
+            r#"{ "$schema": "x", "title": "t", "type": ["string", "null"] }"#,
+        )
+        .unwrap();
+        let err = compile("bounded", r#"{ "properties": { "n": { "minimum": 1 } } }"#).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Unsupported JSON Schema keyword minimum at /properties/n")
+        );
+        compile("bad", r#"{ "type": "text" }"#).unwrap_err();
+        compile("bad", r#"{ "items": [{ "type": "string" }] }"#).unwrap_err();
+    }
+}
@@ -1,5 +1,5 @@
 use bincode2;
-use cosmwasm_std::CanonicalAddr;
+use cosmwasm_std::{Binary, BlockInfo, CanonicalAddr, Coin, Uint128};
 use serde::{Deserialize, Serialize};
 use std::any::type_name;
 
@@ -13,13 +13,39 @@ use secret_toolkit::storage::{AppendStore, AppendStoreMut};
 use serde::de::DeserializeOwned;
 
 pub static CONFIG_KEY: &[u8] = b"config";
+pub static CONTRACT_VERSION_KEY: &[u8] = b"contract_version";
+pub static RELAYERS_KEY: &[u8] = b"relayers";
+pub static FEES_COLLECTED_KEY: &[u8] = b"fees_collected";
+pub static PREFIX_BLOCKS: &[u8] = b"blocks";
+pub static PREFIX_FILES: &[u8] = b"files";
+pub static PREFIX_METADATA: &[u8] = b"metadata";
+pub static PREFIX_INDEXES: &[u8] = b"indexes";
+/// Namespace of the block index before storage keys were made binary
+pub static LEGACY_PREFIX_BLOCK_INDEX: &[u8] = b"block_index";
+pub static PREFIX_SIGNATURES: &[u8] = b"signatures";
+pub static PREFIX_ATTESTATIONS: &[u8] = b"attestations";
+pub static PREFIX_ATTESTERS: &[u8] = b"attesters";
+pub static PREFIX_CREDENTIALS: &[u8] = b"credentials";
+pub static PREFIX_DIDS: &[u8] = b"dids";
+pub static PREFIX_STATUS_LISTS: &[u8] = b"status_lists";
+pub static PREFIX_SWAPS: &[u8] = b"swaps";
+pub static PREFIX_SWAP_BY_CID: &[u8] = b"swap_by_cid";
+pub static PREFIX_PROVENANCE: &[u8] = b"provenance";
+pub static PREFIX_MINT_NONCES: &[u8] = b"mint_nonces";
+pub static PREFIX_NFTS: &[u8] = b"nfts";
+pub static PREFIX_NFT_BY_CID: &[u8] = b"nft_by_cid";
+pub static PREFIX_NFT_OPERATORS: &[u8] = b"nft_operators";
+pub static PREFIX_VIEWING_KEYS: &[u8] = b"viewing_keys";
+pub static PREFIX_USAGE: &[u8] = b"usage";
+pub static PREFIX_SCHEMAS: &[u8] = b"schemas";
+pub static PREFIX_SCHEMA_LIST: &[u8] = b"schema_list";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct File {
     pub content_type: String,
     pub cid: String,
     pub path: String,
-    pub content: Vec<u8>,
+    pub content: Binary,
     pub mode: String,
     pub size: u64,
 }
@@ -45,7 +71,294 @@ pub struct MetadataSchema {
     pub sources: Vec<String>,
     pub parent: String,
     pub refs: Vec<String>,
-    //pub owner: String
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub verified_credential: Option<String>,
+    #[serde(default)]
+    pub licenses: Option<Vec<SourceLicense>>,
+    /// CID of a registered schema the document must match
+    #[serde(default)]
+    pub schema: Option<String>,
+}
+
+/// Licensing terms for one of the `sources` of a metadata document, stored as its own block
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SourceLicense {
+    pub source: String,
+    /// SPDX identifier, or the CID of the license text
+    pub license: String,
+    pub royalty_bps: u16,
+    pub payee: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureAlgo {
+    Secp256k1,
+    Ed25519,
+    RsaPkcs1v15,
+    RsaPss,
+}
+
+/// Signature by the author over the DAG-CBOR bytes of a block
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Signature {
+    pub pubkey: Binary,
+    pub algo: SignatureAlgo,
+    pub sig: Binary,
+}
+
+/// Detached signature over an existing CID and a claim about it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Attestation {
+    pub pubkey: Binary,
+    pub algo: SignatureAlgo,
+    pub signature: Binary,
+    pub claim: String,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialState {
+    Verified,
+    Revoked,
+    Suspended,
+}
+
+/// `statusPurpose` of a status list; revocations cannot be undone
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusPurpose {
+    Revocation,
+    Suspension,
+}
+
+impl Default for StatusPurpose {
+    fn default() -> Self {
+        StatusPurpose::Revocation
+    }
+}
+
+/// Position of a credential in a status list of its issuer
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatusListRef {
+    pub issuer: String,
+    pub list_id: String,
+    pub purpose: StatusPurpose,
+    pub index: u32,
+}
+
+/// Outcome of validating the verified credential of a metadata block
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CredentialRecord {
+    pub id: Option<String>,
+    pub issuer: String,
+    pub subject: String,
+    pub issued_at: Option<u64>,
+    pub not_before: Option<u64>,
+    pub expires: Option<u64>,
+    pub verified_at: u64,
+    pub status: CredentialState,
+    pub status_list: Option<StatusListRef>,
+}
+
+/// Status bitmap of an issuer DID, updated by the controllers of that DID
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatusList {
+    pub purpose: StatusPurpose,
+    pub bits: Vec<u8>,
+}
+
+/// Public key registered under a DID
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VerificationMethod {
+    pub id: String,
+    pub algo: SignatureAlgo,
+    pub public_key: Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Service {
+    pub id: String,
+    pub service_type: String,
+    pub endpoint: String,
+}
+
+/// A registered `did:xdv` identifier; the DID document is built from it on resolution
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DidRecord {
+    pub controllers: Vec<CanonicalAddr>,
+    pub verification_methods: Vec<VerificationMethod>,
+    pub services: Vec<Service>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapState {
+    Locked,
+    Claimed,
+    Refunded,
+}
+
+/// Hash time locked transfer of a CID to another chain, keyed by its hashlock. The token minted for the
+/// CID is held by the contract while the swap is locked and returned to `holder` on refund.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Swap {
+    pub cid: String,
+    pub token_id: String,
+    pub holder: CanonicalAddr,
+    pub sender: CanonicalAddr,
+    pub target_chain: String,
+    pub recipient: String,
+    pub hashlock: Binary,
+    pub timeout_height: u64,
+    pub state: SwapState,
+    pub preimage: Option<Binary>,
+    pub updated_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RelayerKey {
+    pub pubkey: Binary,
+    pub algo: SignatureAlgo,
+}
+
+/// Keys allowed to relay cross-chain mints and how many of them must sign each one
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RelayerSet {
+    pub keys: Vec<RelayerKey>,
+    pub threshold: u32,
+}
+
+/// Where a block minted from another chain came from
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Provenance {
+    pub source_chain: String,
+    pub tx_hash: String,
+    pub nonce: u64,
+    pub relayers: Vec<Binary>,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Expiration {
+    AtHeight(u64),
+    AtTime(u64),
+    Never,
+}
+
+/// Height and time that approval expirations are checked against
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+pub struct BlockTime {
+    pub height: u64,
+    pub time: u64,
+}
+
+impl From<&BlockInfo> for BlockTime {
+    fn from(block: &BlockInfo) -> Self {
+        BlockTime {
+            height: block.height,
+            time: block.time,
+        }
+    }
+}
+
+impl Expiration {
+    pub fn is_expired(&self, block: &BlockTime) -> bool {
+        match self {
+            Expiration::AtHeight(height) => block.height >= *height,
+            Expiration::AtTime(time) => block.time >= *time,
+            Expiration::Never => false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftApproval {
+    pub spender: CanonicalAddr,
+    pub expires: Expiration,
+}
+
+/// SNIP-721 token backed by a stored metadata block
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Nft {
+    pub owner: CanonicalAddr,
+    pub metadata_cid: String,
+    pub approvals: Vec<NftApproval>,
+}
+
+/// Index entry kept for every stored CID
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BlockIndex {
+    pub path: String,
+    pub owner: CanonicalAddr,
+    pub signer: Option<Binary>,
+}
+
+/// A schema registered with `RegisterSchema`, stored as a DAG-CBOR block under `cid`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SchemaEntry {
+    pub name: String,
+    pub cid: String,
+    pub owner: CanonicalAddr,
+}
+
+/// Price of a write in one denom: `flat + per_byte * bytes`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeRate {
+    pub denom: String,
+    pub flat: Uint128,
+    pub per_byte: Uint128,
+}
+
+/// Storage limits applied to every address; `None` means unlimited
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, JsonSchema)]
+pub struct Quotas {
+    pub max_bytes: Option<u64>,
+    pub max_writes_per_block: Option<u32>,
+}
+
+/// What an address has written so far
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, JsonSchema)]
+pub struct Usage {
+    pub bytes: u64,
+    pub blocks: u64,
+    pub last_height: u64,
+    pub writes_at_height: u32,
+}
+
+/// Checks applied to written files and metadata; `None` or an empty list means unrestricted
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, JsonSchema)]
+pub struct ValidationRules {
+    pub max_content_size: Option<u64>,
+    pub max_path_length: Option<u32>,
+    pub allowed_content_types: Vec<String>,
+    #[serde(default)]
+    pub link_policy: LinkPolicy,
+    /// Reject paths that do not start with `/`
+    #[serde(default)]
+    pub require_absolute_paths: bool,
+}
+
+/// Which links a metadata or DAG document may contain
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkPolicy {
+    /// Any valid CID
+    Any,
+    /// Only CIDs stored in this contract
+    LocalOnly,
+    /// Only CIDs with one of these codecs, by multicodec name such as `dag-cbor` or `raw`
+    AllowCodecs(Vec<String>),
+}
+
+impl Default for LinkPolicy {
+    fn default() -> Self {
+        LinkPolicy::Any
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -53,6 +366,12 @@ pub struct State {
     pub owner: CanonicalAddr,
     pub tag: String,
     pub paused: bool,
+    #[serde(default)]
+    pub fees: Vec<FeeRate>,
+    #[serde(default)]
+    pub quotas: Quotas,
+    #[serde(default)]
+    pub validation: ValidationRules,
 }
 
 pub fn config<S: Storage>(storage: &mut S) -> Singleton<S, State> {
@@ -63,6 +382,384 @@ pub fn config_read<S: Storage>(storage: &S) -> ReadonlySingleton<S, State> {
     singleton_read(storage, CONFIG_KEY)
 }
 
+/// Name and version of the code that last wrote the store, checked by `migrate`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractVersion {
+    pub name: String,
+    pub version: String,
+}
+
+pub fn contract_version<S: Storage>(storage: &mut S) -> Singleton<S, ContractVersion> {
+    singleton(storage, CONTRACT_VERSION_KEY)
+}
+
+pub fn contract_version_read<S: Storage>(storage: &S) -> ReadonlySingleton<S, ContractVersion> {
+    singleton_read(storage, CONTRACT_VERSION_KEY)
+}
+
+pub fn relayers<S: Storage>(storage: &mut S) -> Singleton<S, RelayerSet> {
+    singleton(storage, RELAYERS_KEY)
+}
+
+pub fn relayers_read<S: Storage>(storage: &S) -> ReadonlySingleton<S, RelayerSet> {
+    singleton_read(storage, RELAYERS_KEY)
+}
+
+pub fn save_block_index<S: Storage>(storage: &mut S, cid: &Cid, index: &BlockIndex) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_INDEXES, storage);
+    save_to_store(&mut store, &cid.to_bytes(), index)
+}
+
+pub fn may_load_block_index<S: ReadonlyStorage>(
+    storage: &S,
+    cid: &Cid,
+) -> StdResult<Option<BlockIndex>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_INDEXES, storage);
+    may_load(&store, &cid.to_bytes())
+}
+
+/// Block bytes are stored once per CID, whatever paths they were written under
+pub fn save_block_data<S: Storage>(storage: &mut S, cid: &Cid, data: &[u8]) {
+    let mut store = PrefixedStorage::new(PREFIX_BLOCKS, storage);
+    store.set(&cid.to_bytes(), data);
+}
+
+pub fn may_load_block_data<S: ReadonlyStorage>(storage: &S, cid: &Cid) -> Option<Vec<u8>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_BLOCKS, storage);
+    store.get(&cid.to_bytes())
+}
+
+/// Marks `cid` as written under `path` in the `files` or `metadata` namespace
+pub fn save_path<S: Storage>(
+    storage: &mut S,
+    namespace: &[u8],
+    cid: &Cid,
+    path: &str,
+) -> StdResult<()> {
+    let key = path_key(cid, path)?;
+    let mut store = PrefixedStorage::new(namespace, storage);
+    store.set(&key, &[1]);
+    Ok(())
+}
+
+pub fn has_path<S: ReadonlyStorage>(
+    storage: &S,
+    namespace: &[u8],
+    cid: &Cid,
+    path: &str,
+) -> StdResult<bool> {
+    let key = path_key(cid, path)?;
+    let store = ReadonlyPrefixedStorage::new(namespace, storage);
+    Ok(store.get(&key).is_some())
+}
+
+/// Binary `(cid, path)` key: the CID bytes and every `/` separated path segment, each prefixed with its
+/// length as a big endian u16, so no two pairs share a key
+pub fn path_key(cid: &Cid, path: &str) -> StdResult<Vec<u8>> {
+    let cid = cid.to_bytes();
+    let mut key = Vec::with_capacity(2 + cid.len() + 2 + path.len());
+    push_segment(&mut key, &cid)?;
+    for segment in path.split('/') {
+        push_segment(&mut key, segment.as_bytes())?;
+    }
+    Ok(key)
+}
+
+fn push_segment(key: &mut Vec<u8>, segment: &[u8]) -> StdResult<()> {
+    if segment.len() > u16::MAX as usize {
+        return Err(StdError::generic_err("Storage key segment is too long"));
+    }
+    key.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+    key.extend_from_slice(segment);
+    Ok(())
+}
+
+/// Removes and returns the block stored under the old `"<cid>::<path>"` string key
+pub fn take_legacy_block<S: Storage>(
+    storage: &mut S,
+    cid: &str,
+    path: &str,
+) -> StdResult<Option<Vec<u8>>> {
+    let key = format!("{}::{}", cid, path);
+    let data = may_load(storage, key.as_bytes())?;
+    storage.remove(key.as_bytes());
+    Ok(data)
+}
+
+/// Moves a block index entry out of the legacy `block_index` namespace
+pub fn move_legacy_index<S: Storage>(storage: &mut S, cid: &Cid) -> StdResult<()> {
+    let key = cid.to_bytes();
+    let index: Option<BlockIndex> =
+        may_load(&ReadonlyPrefixedStorage::new(LEGACY_PREFIX_BLOCK_INDEX, storage), &key)?;
+    if let Some(index) = index {
+        if may_load_block_index(storage, cid)?.is_none() {
+            save_block_index(storage, cid, &index)?;
+        }
+        PrefixedStorage::new(LEGACY_PREFIX_BLOCK_INDEX, storage).remove(&key);
+    }
+    Ok(())
+}
+
+/// Appends a signature unless the same key already signed the CID with the same bytes
+pub fn append_signature<S: Storage>(storage: &mut S, cid: &Cid, signature: &Signature) -> StdResult<()> {
+    let cid_key = cid.to_bytes();
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_SIGNATURES, &cid_key], storage);
+    let mut store = AppendStoreMut::<Signature, _>::attach_or_create(&mut store)?;
+    for stored in store.iter() {
+        let stored = stored?;
+        if stored.pubkey == signature.pubkey && stored.sig == signature.sig {
+            return Ok(());
+        }
+    }
+    store.push(signature)
+}
+
+pub fn load_signatures<S: ReadonlyStorage>(storage: &S, cid: &Cid) -> StdResult<Vec<Signature>> {
+    let cid_key = cid.to_bytes();
+    let store = ReadonlyPrefixedStorage::multilevel(&[PREFIX_SIGNATURES, &cid_key], storage);
+    let store = match AppendStore::<Signature, _>::attach(&store) {
+        Some(store) => store?,
+        None => return Ok(vec![]),
+    };
+    store.iter().collect()
+}
+
+/// Appends an attestation and records the claim its key made about the CID
+pub fn append_attestation<S: Storage>(
+    storage: &mut S,
+    cid: &Cid,
+    attestation: &Attestation,
+) -> StdResult<()> {
+    let cid_key = cid.to_bytes();
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_ATTESTERS, &cid_key], storage);
+    save_to_store(&mut store, attestation.pubkey.as_slice(), &attestation.claim)?;
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_ATTESTATIONS, &cid_key], storage);
+    let mut store = AppendStoreMut::attach_or_create(&mut store)?;
+    store.push(attestation)
+}
+
+/// The claim `pubkey` attested about a CID, if it attested it
+pub fn may_load_attested_claim<S: ReadonlyStorage>(
+    storage: &S,
+    cid: &Cid,
+    pubkey: &[u8],
+) -> StdResult<Option<String>> {
+    let cid_key = cid.to_bytes();
+    let store = ReadonlyPrefixedStorage::multilevel(&[PREFIX_ATTESTERS, &cid_key], storage);
+    may_load(&store, pubkey)
+}
+
+/// Attestations of a CID after position `start_after`, at most `limit`
+pub fn load_attestations<S: ReadonlyStorage>(
+    storage: &S,
+    cid: &Cid,
+    start_after: Option<u32>,
+    limit: u32,
+) -> StdResult<Vec<Attestation>> {
+    let cid_key = cid.to_bytes();
+    let store = ReadonlyPrefixedStorage::multilevel(&[PREFIX_ATTESTATIONS, &cid_key], storage);
+    load_page(&store, start_after, limit)
+}
+
+/// Items of the append store in `storage` after position `start_after`, at most `limit`
+fn load_page<T: Serialize + DeserializeOwned, S: ReadonlyStorage>(
+    storage: &S,
+    start_after: Option<u32>,
+    limit: u32,
+) -> StdResult<Vec<T>> {
+    let store = match AppendStore::<T, _>::attach(storage) {
+        Some(store) => store?,
+        None => return Ok(vec![]),
+    };
+    let start = start_after.map_or(0, |position| position as usize + 1);
+    store.iter().skip(start).take(limit as usize).collect()
+}
+
+pub fn save_credential<S: Storage>(
+    storage: &mut S,
+    cid: &Cid,
+    record: &CredentialRecord,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_CREDENTIALS, storage);
+    save_to_store(&mut store, &cid.to_bytes(), record)
+}
+
+pub fn may_load_credential<S: ReadonlyStorage>(
+    storage: &S,
+    cid: &Cid,
+) -> StdResult<Option<CredentialRecord>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_CREDENTIALS, storage);
+    may_load(&store, &cid.to_bytes())
+}
+
+pub fn save_did<S: Storage>(storage: &mut S, did: &str, record: &DidRecord) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_DIDS, storage);
+    save_to_store(&mut store, did.as_bytes(), record)
+}
+
+pub fn may_load_did<S: ReadonlyStorage>(storage: &S, did: &str) -> StdResult<Option<DidRecord>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_DIDS, storage);
+    may_load(&store, did.as_bytes())
+}
+
+pub fn save_status_list<S: Storage>(
+    storage: &mut S,
+    issuer: &str,
+    list_id: &str,
+    list: &StatusList,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_STATUS_LISTS, issuer.as_bytes()], storage);
+    save_to_store(&mut store, list_id.as_bytes(), list)
+}
+
+pub fn may_load_status_list<S: ReadonlyStorage>(
+    storage: &S,
+    issuer: &str,
+    list_id: &str,
+) -> StdResult<Option<StatusList>> {
+    let store = ReadonlyPrefixedStorage::multilevel(&[PREFIX_STATUS_LISTS, issuer.as_bytes()], storage);
+    may_load(&store, list_id.as_bytes())
+}
+
+/// Registers a schema and appends it to the list `ListSchemas` returns
+pub fn save_schema<S: Storage>(storage: &mut S, cid: &Cid, entry: &SchemaEntry) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_SCHEMAS, storage);
+    save_to_store(&mut store, &cid.to_bytes(), entry)?;
+    let mut store = PrefixedStorage::new(PREFIX_SCHEMA_LIST, storage);
+    let mut store = AppendStoreMut::attach_or_create(&mut store)?;
+    store.push(entry)
+}
+
+pub fn may_load_schema<S: ReadonlyStorage>(storage: &S, cid: &Cid) -> StdResult<Option<SchemaEntry>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_SCHEMAS, storage);
+    may_load(&store, &cid.to_bytes())
+}
+
+/// Schemas in registration order after position `start_after`, at most `limit`
+pub fn load_schemas<S: ReadonlyStorage>(
+    storage: &S,
+    start_after: Option<u32>,
+    limit: u32,
+) -> StdResult<Vec<SchemaEntry>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_SCHEMA_LIST, storage);
+    load_page(&store, start_after, limit)
+}
+
+pub fn save_swap<S: Storage>(storage: &mut S, swap: &Swap) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_SWAPS, storage);
+    save_to_store(&mut store, swap.hashlock.as_slice(), swap)
+}
+
+pub fn may_load_swap<S: ReadonlyStorage>(storage: &S, hashlock: &[u8]) -> StdResult<Option<Swap>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_SWAPS, storage);
+    may_load(&store, hashlock)
+}
+
+/// Points a CID at its latest swap
+pub fn save_swap_by_cid<S: Storage>(storage: &mut S, cid: &Cid, hashlock: &Binary) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_SWAP_BY_CID, storage);
+    save_to_store(&mut store, &cid.to_bytes(), hashlock)
+}
+
+pub fn may_load_swap_by_cid<S: ReadonlyStorage>(storage: &S, cid: &Cid) -> StdResult<Option<Binary>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_SWAP_BY_CID, storage);
+    may_load(&store, &cid.to_bytes())
+}
+
+pub fn save_provenance<S: Storage>(storage: &mut S, cid: &Cid, provenance: &Provenance) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_PROVENANCE, storage);
+    save_to_store(&mut store, &cid.to_bytes(), provenance)
+}
+
+pub fn may_load_provenance<S: ReadonlyStorage>(
+    storage: &S,
+    cid: &Cid,
+) -> StdResult<Option<Provenance>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_PROVENANCE, storage);
+    may_load(&store, &cid.to_bytes())
+}
+
+/// Marks a nonce of a source chain as used, returning false if it already was
+pub fn use_mint_nonce<S: Storage>(storage: &mut S, source_chain: &str, nonce: u64) -> bool {
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_MINT_NONCES, source_chain.as_bytes()], storage);
+    let key = nonce.to_be_bytes();
+    if store.get(&key).is_some() {
+        return false;
+    }
+    store.set(&key, &[1]);
+    true
+}
+
+pub fn save_nft<S: Storage>(storage: &mut S, token_id: &str, nft: &Nft) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_NFTS, storage);
+    save_to_store(&mut store, token_id.as_bytes(), nft)
+}
+
+pub fn may_load_nft<S: ReadonlyStorage>(storage: &S, token_id: &str) -> StdResult<Option<Nft>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_NFTS, storage);
+    may_load(&store, token_id.as_bytes())
+}
+
+pub fn save_nft_by_cid<S: Storage>(storage: &mut S, cid: &Cid, token_id: &str) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_NFT_BY_CID, storage);
+    save_to_store(&mut store, &cid.to_bytes(), &token_id.to_string())
+}
+
+pub fn may_load_nft_by_cid<S: ReadonlyStorage>(storage: &S, cid: &Cid) -> StdResult<Option<String>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_NFT_BY_CID, storage);
+    may_load(&store, &cid.to_bytes())
+}
+
+/// Operators an owner approved for all of their tokens
+pub fn save_nft_operators<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    operators: &[NftApproval],
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_NFT_OPERATORS, storage);
+    save_to_store(&mut store, owner.as_slice(), &operators.to_vec())
+}
+
+pub fn load_nft_operators<S: ReadonlyStorage>(
+    storage: &S,
+    owner: &CanonicalAddr,
+) -> StdResult<Vec<NftApproval>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_NFT_OPERATORS, storage);
+    Ok(may_load(&store, owner.as_slice())?.unwrap_or_default())
+}
+
+/// Only the SHA-256 of a viewing key is kept
+pub fn save_viewing_key<S: Storage>(storage: &mut S, owner: &CanonicalAddr, hash: &[u8]) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_VIEWING_KEYS, storage);
+    save_to_store(&mut store, owner.as_slice(), &hash.to_vec())
+}
+
+pub fn may_load_viewing_key<S: ReadonlyStorage>(
+    storage: &S,
+    owner: &CanonicalAddr,
+) -> StdResult<Option<Vec<u8>>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_VIEWING_KEYS, storage);
+    may_load(&store, owner.as_slice())
+}
+
+pub fn load_collected_fees<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<Coin>> {
+    Ok(may_load(storage, FEES_COLLECTED_KEY)?.unwrap_or_default())
+}
+
+pub fn save_collected_fees<S: Storage>(storage: &mut S, fees: &[Coin]) -> StdResult<()> {
+    save_to_store(storage, FEES_COLLECTED_KEY, &fees.to_vec())
+}
+
+pub fn load_usage<S: ReadonlyStorage>(storage: &S, addr: &CanonicalAddr) -> StdResult<Usage> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_USAGE, storage);
+    Ok(may_load(&store, addr.as_slice())?.unwrap_or_default())
+}
+
+pub fn save_usage<S: Storage>(storage: &mut S, addr: &CanonicalAddr, usage: &Usage) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_USAGE, storage);
+    save_to_store(&mut store, addr.as_slice(), usage)
+}
+
 pub fn save_to_store<T: Serialize, S: Storage>(
     storage: &mut S,
     key: &[u8],
@@ -105,3 +802,28 @@ fn get_bin_data<T: DeserializeOwned, S: ReadonlyStorage>(storage: &S, key: &[u8]
             .map_err(|e| StdError::serialize_err(type_name::<T>(), e))?),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn path_keys_do_not_collide() {
+        let cid = Cid::from_str("QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D").unwrap();
+        let keys = vec![
+            path_key(&cid, "/").unwrap(),
+            path_key(&cid, "/a/b").unwrap(),
+            path_key(&cid, "/a//b").unwrap(),
+            path_key(&cid, "/a/b/").unwrap(),
+            path_key(&cid, "/ab").unwrap(),
+            path_key(&cid, "a/b").unwrap(),
+        ];
+        for (i, a) in keys.iter().enumerate() {
+            for b in &keys[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+        assert!(path_key(&cid, "/").unwrap().starts_with(&[0, 34]));
+    }
+}
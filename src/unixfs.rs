@@ -0,0 +1,191 @@
+use std::iter::Peekable;
+
+use cosmwasm_std::{StdError, StdResult};
+use libipld::{
+    cid::multihash::{Code, MultihashDigest},
+    Cid,
+};
+
+use crate::car::write_varint;
+
+/// Chunk size of the go-ipfs default chunker, `size-262144`
+pub const CHUNK_SIZE: usize = 256 * 1024;
+/// Links per node of the go-ipfs balanced layout
+pub const MAX_LINKS: usize = 174;
+
+/// UnixFS `Data.DataType` of a file
+const TYPE_FILE: u64 = 2;
+
+/// A UnixFS v1 node encoded as DAG-PB
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    /// CIDv0, as `ipfs add` returns by default
+    pub cid: Cid,
+    pub data: Vec<u8>,
+    /// Bytes of the file under this node
+    pub file_size: u64,
+    /// Size of this node plus every node under it, the `Tsize` of links to it
+    pub dag_size: u64,
+}
+
+/// Encodes `content` the way `ipfs add` does with its defaults: 256 KiB chunks in UnixFS leaves and a
+/// balanced tree of at most 174 links per node. Returns every node, the root last.
+pub fn encode_file(content: &[u8]) -> StdResult<Vec<Node>> {
+    build(content, CHUNK_SIZE, MAX_LINKS)
+}
+
+/// go-unixfs `balanced.Layout`: the tree grows one level at a time, each new root taking the previous one as
+/// its first child and filling the rest with subtrees of the same depth.
+fn build(content: &[u8], chunk_size: usize, max_links: usize) -> StdResult<Vec<Node>> {
+    let mut chunks = content.chunks(chunk_size).peekable();
+    let mut nodes = vec![leaf(chunks.next().unwrap_or(&[]))?];
+    let mut depth = 1;
+    while chunks.peek().is_some() {
+        // the root so far is always the last node
+        let mut children = vec![nodes.len() - 1];
+        fill(&mut chunks, &mut nodes, &mut children, depth, max_links)?;
+        let root = branch(&nodes, &children)?;
+        nodes.push(root);
+        depth += 1;
+    }
+    Ok(nodes)
+}
+
+/// Adds subtrees `depth` levels deep to `children`, indexes into `nodes`, until it is full or the chunks end
+fn fill<'a, I: Iterator<Item = &'a [u8]>>(
+    chunks: &mut Peekable<I>,
+    nodes: &mut Vec<Node>,
+    children: &mut Vec<usize>,
+    depth: u32,
+    max_links: usize,
+) -> StdResult<()> {
+    while children.len() < max_links && chunks.peek().is_some() {
+        let child = if depth == 1 {
+            match chunks.next() {
+                Some(chunk) => leaf(chunk)?,
+                None => break,
+            }
+        } else {
+            let mut grandchildren = vec![];
+            fill(chunks, nodes, &mut grandchildren, depth - 1, max_links)?;
+            branch(nodes, &grandchildren)?
+        };
+        nodes.push(child);
+        children.push(nodes.len() - 1);
+    }
+    Ok(())
+}
+
+fn leaf(chunk: &[u8]) -> StdResult<Node> {
+    let mut unixfs = vec![];
+    write_varint_field(&mut unixfs, 1, TYPE_FILE);
+    if !chunk.is_empty() {
+        write_bytes_field(&mut unixfs, 2, chunk);
+    }
+    write_varint_field(&mut unixfs, 3, chunk.len() as u64);
+
+    let mut data = vec![];
+    write_bytes_field(&mut data, 1, &unixfs);
+    node(data, chunk.len() as u64, 0)
+}
+
+fn branch(nodes: &[Node], children: &[usize]) -> StdResult<Node> {
+    let children = children.iter().map(|i| &nodes[*i]).collect::<Vec<_>>();
+    let file_size = children.iter().map(|child| child.file_size).sum();
+
+    let mut unixfs = vec![];
+    write_varint_field(&mut unixfs, 1, TYPE_FILE);
+    write_varint_field(&mut unixfs, 3, file_size);
+    for child in &children {
+        write_varint_field(&mut unixfs, 4, child.file_size);
+    }
+
+    // DAG-PB puts links before data; go-ipfs always writes the empty name
+    let mut data = vec![];
+    for child in &children {
+        let mut link = vec![];
+        write_bytes_field(&mut link, 1, &child.cid.to_bytes());
+        write_bytes_field(&mut link, 2, b"");
+        write_varint_field(&mut link, 3, child.dag_size);
+        write_bytes_field(&mut data, 2, &link);
+    }
+    write_bytes_field(&mut data, 1, &unixfs);
+    let linked = children.iter().map(|child| child.dag_size).sum();
+    node(data, file_size, linked)
+}
+
+fn node(data: Vec<u8>, file_size: u64, linked: u64) -> StdResult<Node> {
+    let cid = Cid::new_v0(Code::Sha2_256.digest(&data))
+        .map_err(|e| StdError::generic_err(format!("Invalid UnixFS CID: {}", e)))?;
+    Ok(Node {
+        cid,
+        dag_size: data.len() as u64 + linked,
+        data,
+        file_size,
+    })
+}
+
+fn write_varint_field(buf: &mut Vec<u8>, field: u64, value: u64) {
+    write_varint(buf, field << 3);
+    write_varint(buf, value);
+}
+
+fn write_bytes_field(buf: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    write_varint(buf, field << 3 | 2);
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn matches_ipfs_add() {
+        let hello = encode_file(b"hello world\n").unwrap();
+        assert_eq!(hello.len(), 1);
+        assert_eq!(
+            hello[0].cid,
+            Cid::from_str("QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o").unwrap()
+        );
+
+        let empty = encode_file(b"").unwrap();
+        assert_eq!(
+            empty[0].cid,
+            Cid::from_str("QmbFMke1KXqnYyBBWxB74N4c5SBnJMVAiMNRcGu6x1AwQH").unwrap()
+        );
+
+        // 1 MiB of `i % 251`, four chunks under one root:
+        // python3 -c 'import sys; sys.stdout.buffer.write(bytes(i % 251 for i in range(1 << 20)))' \
+        //     | ipfs add --cid-version=0 -Q
+        let content = (0..1 << 20).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let nodes = encode_file(&content).unwrap();
+        assert_eq!(nodes.len(), 5);
+        let root = nodes.last().unwrap();
+        assert_eq!(
+            root.cid,
+            Cid::from_str("QmXgkY4miMKJBrg8YYke4xw6C2n8WNsUc1GXLhN84k4QM3").unwrap()
+        );
+        assert_eq!(root.file_size, 1 << 20);
+        assert_eq!(root.dag_size, 1_048_832);
+    }
+
+    #[test]
+    fn balanced_layout() {
+        // one byte per leaf and two links per node:
+        // root -> [[[a, b], [c, d]], [[e]]]
+        let nodes = build(b"abcde", 1, 2).unwrap();
+        assert_eq!(nodes.len(), 11);
+        let root = nodes.last().unwrap();
+        assert_eq!(root.file_size, 5);
+        assert_eq!(
+            root.dag_size,
+            nodes.iter().map(|node| node.data.len() as u64).sum::<u64>()
+        );
+
+        let chunks = encode_file(&vec![7; CHUNK_SIZE * 2 + 1]).unwrap();
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[3].file_size, CHUNK_SIZE as u64 * 2 + 1);
+    }
+}
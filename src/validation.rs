@@ -0,0 +1,220 @@
+use cosmwasm_std::{StdError, StdResult};
+use libipld::Cid;
+
+use crate::events::codec_name;
+use crate::state::{LinkPolicy, ValidationRules};
+
+/// Paths are `/` separated and may not contain `..` segments or the `::` key separator; the rules can also
+/// require them to be absolute
+pub fn check_path(rules: &ValidationRules, path: &str) -> StdResult<()> {
+    if let Some(max) = rules.max_path_length {
+        if path.len() > max as usize {
+            return Err(StdError::generic_err(format!(
+                "Path is {} bytes long, the maximum is {}",
+                path.len(),
+                max
+            )));
+        }
+    }
+    if rules.require_absolute_paths && !path.starts_with('/') {
+        return Err(StdError::generic_err(format!(
+            "Path {} must be absolute",
+            path
+        )));
+    }
+    if path.contains("::") {
+        return Err(StdError::generic_err(format!(
+            "Path {} cannot contain ::",
+            path
+        )));
+    }
+    if path.split('/').any(|segment| segment == "..") {
+        return Err(StdError::generic_err(format!(
+            "Path {} cannot contain .. segments",
+            path
+        )));
+    }
+    if path.chars().any(|c| c.is_control()) {
+        return Err(StdError::generic_err(
+            "Path cannot contain control characters",
+        ));
+    }
+    Ok(())
+}
+
+pub fn check_content_size(rules: &ValidationRules, size: usize) -> StdResult<()> {
+    match rules.max_content_size {
+        Some(max) if size as u64 > max => Err(StdError::generic_err(format!(
+            "Content is {} bytes, the maximum is {}",
+            size, max
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// `content_type` must be a `type/subtype` MIME type, and one of the allowed ones when a list is set.
+/// Parameters (`; charset=utf-8`) are ignored and `type/*` entries allow a whole type.
+pub fn check_content_type(rules: &ValidationRules, content_type: &str) -> StdResult<()> {
+    let essence = mime_essence(content_type)
+        .ok_or_else(|| StdError::generic_err(format!("Invalid MIME type {}", content_type)))?;
+    if rules.allowed_content_types.is_empty() {
+        return Ok(());
+    }
+
+    let allowed = rules.allowed_content_types.iter().any(|allowed| {
+        let allowed = allowed.to_ascii_lowercase();
+        match allowed.strip_suffix("/*") {
+            Some(top) => essence.split('/').next() == Some(top),
+            None => allowed == essence,
+        }
+    });
+    if !allowed {
+        return Err(StdError::generic_err(format!(
+            "Content type {} is not allowed",
+            content_type
+        )));
+    }
+    Ok(())
+}
+
+/// Checks the links of a document against `policy`. `is_stored` tells whether a CID was written to this
+/// contract; every missing CID is listed in the error.
+pub fn check_links<F: Fn(&Cid) -> StdResult<bool>>(
+    policy: &LinkPolicy,
+    links: &[Cid],
+    is_stored: F,
+) -> StdResult<()> {
+    match policy {
+        LinkPolicy::Any => Ok(()),
+        LinkPolicy::LocalOnly => {
+            let mut missing: Vec<Cid> = vec![];
+            for link in links {
+                if !missing.contains(link) && !is_stored(link)? {
+                    missing.push(*link);
+                }
+            }
+            if missing.is_empty() {
+                return Ok(());
+            }
+            let missing = missing.iter().map(|cid| cid.to_string()).collect::<Vec<_>>();
+            Err(StdError::generic_err(format!(
+                "Links are not stored in this contract: {}",
+                missing.join(", ")
+            )))
+        }
+        LinkPolicy::AllowCodecs(codecs) => {
+            for link in links {
+                let codec = codec_name(link.codec());
+                if !codecs.iter().any(|allowed| *allowed == codec) {
+                    return Err(StdError::generic_err(format!(
+                        "Codec {} of link {} is not allowed",
+                        codec, link
+                    )));
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Rejects rules that could never be met, such as a malformed allowed MIME type
+pub fn check_rules(rules: &ValidationRules) -> StdResult<()> {
+    for allowed in &rules.allowed_content_types {
+        let valid = match allowed.strip_suffix("/*") {
+            Some(top) => is_token(top),
+            None => mime_essence(allowed).is_some(),
+        };
+        if !valid {
+            return Err(StdError::generic_err(format!(
+                "Invalid allowed MIME type {}",
+                allowed
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Lowercased `type/subtype` of a MIME type, `None` when it is malformed
+fn mime_essence(content_type: &str) -> Option<String> {
+    let essence = content_type.split(';').next()?.trim();
+    let mut parts = essence.splitn(2, '/');
+    let (top, sub) = (parts.next()?, parts.next()?);
+    if !is_token(top) || !is_token(sub) {
+        return None;
+    }
+    Some(essence.to_ascii_lowercase())
+}
+
+/// RFC 6838 restricted name characters
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= 127
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn paths() {
+        let rules = ValidationRules {
+            max_path_length: Some(16),
+            require_absolute_paths: true,
+            ..ValidationRules::default()
+        };
+        check_path(&rules, "/").unwrap();
+        check_path(&rules, "/docs/a.txt").unwrap();
+        check_path(&rules, "docs/a.txt").unwrap_err();
+        check_path(&ValidationRules::default(), "docs/a.txt").unwrap();
+        check_path(&rules, "/docs/../a.txt").unwrap_err();
+        check_path(&rules, "/docs::a").unwrap_err();
+        check_path(&rules, "/a/very/long/path.txt").unwrap_err();
+        check_path(&ValidationRules::default(), "/a/very/long/path.txt").unwrap();
+    }
+
+    #[test]
+    fn content_types() {
+        let any = ValidationRules::default();
+        check_content_type(&any, "text/plain; charset=utf-8").unwrap();
+        check_content_type(&any, "text").unwrap_err();
+        check_content_type(&any, "text/pl ain").unwrap_err();
+
+        let rules = ValidationRules {
+            allowed_content_types: vec!["image/*".to_string(), "application/json".to_string()],
+            ..ValidationRules::default()
+        };
+        check_rules(&rules).unwrap();
+        check_content_type(&rules, "image/png").unwrap();
+        check_content_type(&rules, "Application/JSON").unwrap();
+        check_content_type(&rules, "text/plain").unwrap_err();
+
+        check_rules(&ValidationRules {
+            allowed_content_types: vec!["image".to_string()],
+            ..ValidationRules::default()
+        })
+        .unwrap_err();
+    }
+
+    #[test]
+    fn links() {
+        let stored = Cid::from_str("QmSnuWmxptJZdLJpKRarxBMS2Ju2oANVrgbr2xWbie9b2D").unwrap();
+        let missing = Cid::from_str("QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o").unwrap();
+        let is_stored = |cid: &Cid| Ok(*cid == stored);
+        let links = vec![stored, missing, missing];
+
+        check_links(&LinkPolicy::Any, &links, is_stored).unwrap();
+        check_links(&LinkPolicy::LocalOnly, &links[..1], is_stored).unwrap();
+        assert_eq!(
+            check_links(&LinkPolicy::LocalOnly, &links, is_stored).unwrap_err(),
+            StdError::generic_err(format!("Links are not stored in this contract: {}", missing))
+        );
+
+        let dag_pb = LinkPolicy::AllowCodecs(vec!["dag-pb".to_string()]);
+        check_links(&dag_pb, &links, is_stored).unwrap();
+        let raw = LinkPolicy::AllowCodecs(vec!["raw".to_string()]);
+        check_links(&raw, &links, is_stored).unwrap_err();
+    }
+}
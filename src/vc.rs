@@ -0,0 +1,206 @@
+use cosmwasm_std::{StdError, StdResult};
+use serde::Deserialize;
+
+use crate::crypto::verify_signature;
+use crate::state::{SignatureAlgo, StatusListRef, StatusPurpose};
+
+/// A decoded, not yet verified, VC-JWT
+pub struct Jwt {
+    pub header: JwtHeader,
+    pub claims: JwtClaims,
+    pub signing_input: String,
+    pub signature: Vec<u8>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct JwtHeader {
+    pub alg: String,
+    pub kid: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct JwtClaims {
+    pub iss: String,
+    pub sub: Option<String>,
+    pub jti: Option<String>,
+    pub iat: Option<u64>,
+    pub nbf: Option<u64>,
+    pub exp: Option<u64>,
+    pub vc: CredentialClaim,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct CredentialClaim {
+    #[serde(rename = "credentialSubject")]
+    pub credential_subject: Option<CredentialSubject>,
+    #[serde(rename = "credentialStatus")]
+    pub credential_status: Option<StatusListEntry>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct CredentialSubject {
+    pub id: Option<String>,
+}
+
+/// `StatusList2021Entry`; `statusListCredential` is used as the on-chain list id
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct StatusListEntry {
+    #[serde(rename = "statusPurpose")]
+    pub status_purpose: String,
+    #[serde(rename = "statusListIndex")]
+    pub status_list_index: String,
+    #[serde(rename = "statusListCredential")]
+    pub status_list_credential: String,
+}
+
+impl Jwt {
+    /// `credentialSubject.id`, which the JWT encoding may move into `sub`
+    pub fn subject(&self) -> Option<&str> {
+        self.claims
+            .vc
+            .credential_subject
+            .as_ref()
+            .and_then(|s| s.id.as_deref())
+            .or(self.claims.sub.as_deref())
+    }
+
+    /// The status list entry of the credential, if it has one
+    pub fn status_list(&self) -> StdResult<Option<StatusListRef>> {
+        let entry = match &self.claims.vc.credential_status {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let index = entry
+            .status_list_index
+            .parse::<u32>()
+            .map_err(|e| StdError::parse_err("statusListIndex", e))?;
+        let purpose = match entry.status_purpose.as_str() {
+            "revocation" => StatusPurpose::Revocation,
+            "suspension" => StatusPurpose::Suspension,
+            other => {
+                return Err(StdError::generic_err(format!(
+                    "Unsupported statusPurpose {}",
+                    other
+                )))
+            }
+        };
+
+        Ok(Some(StatusListRef {
+            issuer: self.claims.iss.clone(),
+            list_id: entry.status_list_credential.clone(),
+            purpose,
+            index,
+        }))
+    }
+}
+
+/// Splits a compact JWS and decodes its header and claims
+pub fn decode_jwt(token: &str) -> StdResult<Jwt> {
+    let parts: Vec<&str> = token.trim().split('.').collect();
+    if parts.len() != 3 {
+        return Err(StdError::generic_err(
+            "Verified credential must be a JWT (JSON-LD proofs are not supported)",
+        ));
+    }
+
+    let header: JwtHeader = serde_json_wasm::from_slice(&decode_segment(parts[0])?)
+        .map_err(|e| StdError::parse_err("JwtHeader", e))?;
+    let claims: JwtClaims = serde_json_wasm::from_slice(&decode_segment(parts[1])?)
+        .map_err(|e| StdError::parse_err("JwtClaims", e))?;
+    let signature = decode_segment(parts[2])?;
+
+    Ok(Jwt {
+        header,
+        claims,
+        signing_input: format!("{}.{}", parts[0], parts[1]),
+        signature,
+    })
+}
+
+fn decode_segment(segment: &str) -> StdResult<Vec<u8>> {
+    base64::decode_config(segment, base64::URL_SAFE_NO_PAD)
+        .map_err(|e| StdError::invalid_base64(e.to_string()))
+}
+
+/// Maps a JWS `alg` to the verifier used for it, including the did-jwt-rsa variants
+pub fn algo_for(alg: &str) -> StdResult<SignatureAlgo> {
+    match alg {
+        "ES256K" => Ok(SignatureAlgo::Secp256k1),
+        "EdDSA" => Ok(SignatureAlgo::Ed25519),
+        "RS256" => Ok(SignatureAlgo::RsaPkcs1v15),
+        "PS256" => Ok(SignatureAlgo::RsaPss),
+        _ => Err(StdError::generic_err(format!(
+            "Unsupported JWT algorithm {}",
+            alg
+        ))),
+    }
+}
+
+/// Checks the JWS signature against an issuer public key
+pub fn verify_jwt(jwt: &Jwt, pubkey: &[u8]) -> StdResult<()> {
+    let algo = algo_for(&jwt.header.alg)?;
+    verify_signature(&algo, pubkey, jwt.signing_input.as_bytes(), &jwt.signature)
+}
+
+/// Checks `nbf` and `exp` against the block time (seconds)
+pub fn check_validity(jwt: &Jwt, now: u64) -> StdResult<()> {
+    if let Some(nbf) = jwt.claims.nbf {
+        if now < nbf {
+            return Err(StdError::generic_err(
+                "Verified credential is not yet valid",
+            ));
+        }
+    }
+    if let Some(exp) = jwt.claims.exp {
+        if now >= exp {
+            return Err(StdError::generic_err("Verified credential has expired"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use k256::ecdsa::signature::Signer;
+
+    pub fn issue_es256k(key: &k256::ecdsa::SigningKey, claims: &str) -> String {
+        let header =
+            base64::encode_config(r#"{"alg":"ES256K","typ":"JWT"}"#, base64::URL_SAFE_NO_PAD);
+        let payload = base64::encode_config(claims, base64::URL_SAFE_NO_PAD);
+        let signing_input = format!("{}.{}", header, payload);
+        let sig: k256::ecdsa::Signature = key.sign(signing_input.as_bytes());
+        format!(
+            "{}.{}",
+            signing_input,
+            base64::encode_config(sig.as_ref(), base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    #[test]
+    fn decode_and_verify() {
+        let key = k256::ecdsa::SigningKey::from_bytes(&[5u8; 32]).unwrap();
+        let pubkey = key.verifying_key().to_bytes();
+        let token = issue_es256k(
+            &key,
+            r#"{"iss":"did:xdv:issuer","nbf":100,"exp":200,"vc":{"@context":["https://www.w3.org/2018/credentials/v1"],"type":["VerifiableCredential"],"credentialSubject":{"id":"did:xdv:holder","name":"Alice"}}}"#,
+        );
+
+        let jwt = decode_jwt(&token).unwrap();
+        assert_eq!(jwt.claims.iss, "did:xdv:issuer");
+        assert_eq!(jwt.subject(), Some("did:xdv:holder"));
+        verify_jwt(&jwt, &pubkey).unwrap();
+
+        let other = k256::ecdsa::SigningKey::from_bytes(&[6u8; 32]).unwrap();
+        verify_jwt(&jwt, &other.verifying_key().to_bytes()).unwrap_err();
+
+        check_validity(&jwt, 150).unwrap();
+        check_validity(&jwt, 99).unwrap_err();
+        check_validity(&jwt, 200).unwrap_err();
+    }
+
+    #[test]
+    fn rejects_non_jwt() {
+        assert!(decode_jwt(r#"{"proof":{"type":"Ed25519Signature2018"}}"#).is_err());
+    }
+}